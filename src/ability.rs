@@ -0,0 +1,416 @@
+use bevy::{
+    color::palettes::css::{AQUA, MEDIUM_PURPLE, YELLOW},
+    prelude::*,
+};
+use bevy_inspector_egui::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chaser::{update_chaser_avoidance, Chaser, CHASER_RADIUS},
+    physics::{SimulationSet, CHASER_GROUP, PLAYER_GROUP, WALL_GROUP},
+    player::{Player, PlayerAction, PlayerInputSet, ResetEvent},
+};
+
+/// Seconds the shockwave ring is drawn for
+const SHOCKWAVE_DRAW_TIME: f32 = 0.25;
+/// Seconds after a shockwave during which a wall hit can stun
+const KNOCKBACK_TIME: f32 = 0.5;
+
+/// Player abilities that push, slow and stun chasers
+pub struct AbilityPlugin;
+
+impl Plugin for AbilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, queue_abilities.after(PlayerInputSet))
+            .add_systems(
+                FixedUpdate,
+                (
+                    (
+                        prepare_player,
+                        reset_abilities,
+                        use_abilities,
+                        update_knockback,
+                        stun_chasers,
+                        update_slow_fields,
+                    )
+                        .chain(),
+                    // Stunned chasers lose their steering force right before physics runs
+                    hold_stunned_chasers.after(update_chaser_avoidance),
+                )
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, draw_abilities)
+            .init_resource::<PendingAbilities>()
+            .register_type::<Abilities>();
+    }
+}
+
+/// Ability parameters and cooldowns. Added to the player.
+#[derive(Component, Debug, Reflect, InspectorOptions)]
+#[reflect(Component, InspectorOptions)]
+pub struct Abilities {
+    /// Chasers in this radius are pushed away
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub shockwave_radius: f32,
+    /// Speed given to chasers next to the player. Falls off to 0 at the edge.
+    #[inspector(min = 0.0, max = 5000.0)]
+    pub shockwave_speed: f32,
+    #[inspector(min = 0.0, max = 60.0)]
+    pub shockwave_cooldown: f32,
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub slow_radius: f32,
+    /// Seconds a slow field lasts
+    #[inspector(min = 0.0, max = 60.0)]
+    pub slow_duration: f32,
+    /// Linear damping of chasers inside a slow field
+    #[inspector(min = 0.0, max = 100.0)]
+    pub slow_damping: f32,
+    #[inspector(min = 0.0, max = 60.0)]
+    pub slow_cooldown: f32,
+    /// Knocked back chasers that hit a wall with more contact force than this are stunned
+    #[inspector(min = 0.0, max = 1_000_000.0)]
+    pub stun_force: f32,
+    #[inspector(min = 0.0, max = 10.0)]
+    pub stun_time: f32,
+    shockwave_timer: Option<Timer>,
+    slow_timer: Option<Timer>,
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Self {
+            shockwave_radius: 140.0,
+            shockwave_speed: 900.0,
+            shockwave_cooldown: 4.0,
+            slow_radius: 120.0,
+            slow_duration: 3.0,
+            slow_damping: 25.0,
+            slow_cooldown: 8.0,
+            stun_force: 8000.0,
+            stun_time: 1.5,
+            shockwave_timer: None,
+            slow_timer: None,
+        }
+    }
+}
+
+/// Tick a cooldown and clear it once it finishes
+fn tick_cooldown(timer: &mut Option<Timer>, time: &Time) {
+    if timer
+        .as_mut()
+        .is_some_and(|t| t.tick(time.delta()).finished())
+    {
+        *timer = None;
+    }
+}
+
+/// Abilities pressed since the last simulation step. Frames without a step must not lose them.
+#[derive(Resource, Debug, Default)]
+struct PendingAbilities {
+    shockwave: bool,
+    slow_field: bool,
+}
+
+/// Pushed by a shockwave. Hitting a wall hard enough in this state stuns.
+#[derive(Component)]
+struct Knockback {
+    timer: Timer,
+    /// Events to restore when the knockback ends
+    active_events: ActiveEvents,
+}
+
+/// Stop the knockback and go back to the chaser's own events
+fn end_knockback(commands: &mut Commands, entity: Entity, knockback: &Knockback) {
+    commands
+        .entity(entity)
+        .remove::<Knockback>()
+        .try_insert(knockback.active_events);
+}
+
+/// Doesn't steer until the timer finishes
+#[derive(Component)]
+struct Stunned(Timer);
+
+/// Chaser inside a slow field
+#[derive(Component)]
+pub struct Slowed {
+    /// Damping to restore when leaving the field
+    pub damping: f32,
+}
+
+/// Zone left behind by the player that slows chasers inside
+#[derive(Component)]
+struct SlowField {
+    radius: f32,
+    damping: f32,
+    lifetime: Timer,
+}
+
+/// Expanding ring drawn where a shockwave went off
+#[derive(Component)]
+struct ShockwaveRing {
+    radius: f32,
+    timer: Timer,
+}
+
+fn prepare_player(mut commands: Commands, q_player: Query<Entity, Added<Player>>) {
+    for entity in q_player.iter() {
+        commands.entity(entity).insert(Abilities::default());
+    }
+}
+
+fn reset_abilities(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    mut q_abilities: Query<&mut Abilities>,
+    q_effects: Query<Entity, Or<(With<SlowField>, With<ShockwaveRing>)>>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for mut abilities in q_abilities.iter_mut() {
+        abilities.shockwave_timer = None;
+        abilities.slow_timer = None;
+    }
+    for entity in q_effects.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn queue_abilities(action: Res<PlayerAction>, mut pending: ResMut<PendingAbilities>) {
+    pending.shockwave |= action.shockwave;
+    pending.slow_field |= action.slow_field;
+}
+
+/// Shockwave on "E" or gamepad West, slow field on "Q" or gamepad North
+fn use_abilities(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pending: ResMut<PendingAbilities>,
+    rapier_context: Res<RapierContext>,
+    mut q_player: Query<(&Transform, &mut Abilities)>,
+    q_chasers: Query<
+        (
+            &Transform,
+            &ReadMassProperties,
+            Option<&ActiveEvents>,
+            Option<&Knockback>,
+        ),
+        With<Chaser>,
+    >,
+) {
+    let Ok((player_transform, mut abilities)) = q_player.get_single_mut() else {
+        return;
+    };
+    tick_cooldown(&mut abilities.shockwave_timer, &time);
+    tick_cooldown(&mut abilities.slow_timer, &time);
+    let PendingAbilities {
+        shockwave,
+        slow_field,
+    } = std::mem::take(&mut *pending);
+
+    let pos = player_transform.translation.xy();
+
+    if shockwave && abilities.shockwave_timer.is_none() {
+        let radius = abilities.shockwave_radius;
+        let filter = QueryFilter::new().groups(CollisionGroups::new(PLAYER_GROUP, CHASER_GROUP));
+        let mut hit = vec![];
+        rapier_context.intersections_with_shape(
+            pos,
+            0.0,
+            &Collider::ball(radius),
+            filter,
+            |entity| {
+                hit.push(entity);
+                true
+            },
+        );
+        for entity in hit {
+            let Ok((transform, read_mass, active_events, knockback)) = q_chasers.get(entity) else {
+                continue;
+            };
+            // Wall hits are detected with contact force events. Keep whatever the chaser
+            // already had, and what it had before an earlier knockback.
+            let active_events = active_events.copied().unwrap_or(ActiveEvents::empty());
+            let restore_events = knockback.map_or(active_events, |k| k.active_events);
+            let offset = transform.translation.xy() - pos;
+            let falloff = 1.0 - (offset.length() / radius).min(1.0);
+            let dir = offset.try_normalize().unwrap_or(Vec2::X);
+            commands.entity(entity).insert((
+                ExternalImpulse {
+                    impulse: dir * abilities.shockwave_speed * falloff * read_mass.mass,
+                    ..default()
+                },
+                Knockback {
+                    timer: Timer::from_seconds(KNOCKBACK_TIME, TimerMode::Once),
+                    active_events: restore_events,
+                },
+                active_events | ActiveEvents::CONTACT_FORCE_EVENTS,
+            ));
+        }
+        commands.spawn((
+            ShockwaveRing {
+                radius,
+                timer: Timer::from_seconds(SHOCKWAVE_DRAW_TIME, TimerMode::Once),
+            },
+            TransformBundle::from_transform(Transform::from_translation(pos.extend(0.0))),
+        ));
+        abilities.shockwave_timer = Some(Timer::from_seconds(
+            abilities.shockwave_cooldown,
+            TimerMode::Once,
+        ));
+    }
+
+    if slow_field && abilities.slow_timer.is_none() {
+        commands.spawn((
+            SlowField {
+                radius: abilities.slow_radius,
+                damping: abilities.slow_damping,
+                lifetime: Timer::from_seconds(abilities.slow_duration, TimerMode::Once),
+            },
+            TransformBundle::from_transform(Transform::from_translation(pos.extend(0.0))),
+            Name::new("SlowField"),
+        ));
+        abilities.slow_timer = Some(Timer::from_seconds(
+            abilities.slow_cooldown,
+            TimerMode::Once,
+        ));
+    }
+}
+
+fn update_knockback(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_knockback: Query<(Entity, &mut Knockback)>,
+) {
+    for (entity, mut knockback) in q_knockback.iter_mut() {
+        if knockback.timer.tick(time.delta()).finished() {
+            end_knockback(&mut commands, entity, &knockback);
+        }
+    }
+}
+
+/// Knocked back chasers that slam into a wall are stunned
+fn stun_chasers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut contact_events: EventReader<ContactForceEvent>,
+    q_abilities: Query<&Abilities>,
+    q_knockback: Query<&Knockback>,
+    q_groups: Query<&CollisionGroups>,
+    mut q_stunned: Query<(Entity, &mut Stunned)>,
+) {
+    for (entity, mut stunned) in q_stunned.iter_mut() {
+        if stunned.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Stunned>();
+        }
+    }
+    let Ok(abilities) = q_abilities.get_single() else {
+        return;
+    };
+
+    for event in contact_events.read() {
+        if event.total_force_magnitude < abilities.stun_force {
+            continue;
+        }
+        let (c1, c2) = (event.collider1, event.collider2);
+        let (chaser, knockback) = if let Ok(knockback) = q_knockback.get(c1) {
+            (c1, knockback)
+        } else if let Ok(knockback) = q_knockback.get(c2) {
+            (c2, knockback)
+        } else {
+            continue;
+        };
+        let other = if chaser == c1 { c2 } else { c1 };
+        let is_wall = q_groups
+            .get(other)
+            .is_ok_and(|groups| groups.memberships.contains(WALL_GROUP));
+        if is_wall {
+            end_knockback(&mut commands, chaser, knockback);
+            commands.entity(chaser).insert(Stunned(Timer::from_seconds(
+                abilities.stun_time,
+                TimerMode::Once,
+            )));
+        }
+    }
+}
+
+fn hold_stunned_chasers(mut q_stunned: Query<&mut ExternalForce, With<Stunned>>) {
+    for mut force in q_stunned.iter_mut() {
+        force.force = Vec2::ZERO;
+    }
+}
+
+/// Raise damping of chasers inside slow fields and restore it once they leave
+fn update_slow_fields(
+    mut commands: Commands,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut q_fields: Query<(Entity, &mut SlowField, &Transform)>,
+    mut q_chasers: Query<(Entity, &mut Damping, Option<&Slowed>), With<Chaser>>,
+) {
+    let mut inside = vec![];
+    for (entity, mut field, transform) in q_fields.iter_mut() {
+        if field.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let filter = QueryFilter::new().groups(CollisionGroups::new(PLAYER_GROUP, CHASER_GROUP));
+        rapier_context.intersections_with_shape(
+            transform.translation.xy(),
+            0.0,
+            &Collider::ball(field.radius),
+            filter,
+            |chaser| {
+                inside.push((chaser, field.damping));
+                true
+            },
+        );
+    }
+
+    for (entity, mut damping, slowed) in q_chasers.iter_mut() {
+        let field_damping = inside
+            .iter()
+            .filter(|&&(chaser, _)| chaser == entity)
+            .map(|&(_, damping)| damping)
+            .reduce(f32::max);
+        match (field_damping, slowed) {
+            (Some(field_damping), None) => {
+                commands.entity(entity).insert(Slowed {
+                    damping: damping.linear_damping,
+                });
+                damping.linear_damping = field_damping;
+            }
+            (None, Some(slowed)) => {
+                damping.linear_damping = slowed.damping;
+                commands.entity(entity).remove::<Slowed>();
+            }
+            _ => (),
+        }
+    }
+}
+
+fn draw_abilities(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    mut q_rings: Query<(Entity, &mut ShockwaveRing, &Transform)>,
+    q_fields: Query<(&SlowField, &Transform)>,
+    q_stunned: Query<&Transform, With<Stunned>>,
+) {
+    for (entity, mut ring, transform) in q_rings.iter_mut() {
+        if ring.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let radius = ring.radius * ring.timer.fraction();
+        gizmos.circle_2d(transform.translation.xy(), radius, AQUA);
+    }
+    for (field, transform) in q_fields.iter() {
+        let color = MEDIUM_PURPLE.with_alpha(1.0 - field.lifetime.fraction() * 0.7);
+        gizmos.circle_2d(transform.translation.xy(), field.radius, color);
+    }
+    for transform in q_stunned.iter() {
+        gizmos.circle_2d(transform.translation.xy(), CHASER_RADIUS * 1.6, YELLOW);
+    }
+}
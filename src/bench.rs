@@ -0,0 +1,443 @@
+use bevy::{ecs::schedule::ExecutorKind, prelude::*, time::TimeUpdateStrategy, utils::Instant};
+use bevy_rapier2d::{plugin::PhysicsSet, prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    chaser::{update_chaser_avoidance, Chaser, ChaserAssets},
+    flowfield::{apply_force, update_cost, update_target},
+    player::{read_player_action, Player, PlayerAction, PlayerInputSet},
+    replay::{advance_playback, start_playback},
+    rng::GameRng,
+    shooter::ShooterAssets,
+    spawner::{spawn_enemy, EnemyKind},
+    tilemap::{load_tilemap, Args, TileStorage, Tilemap},
+};
+
+/// Simulated time per frame, unless the input is a replay
+const BENCH_FRAME_TIME: f64 = 1.0 / 60.0;
+/// Chasers aren't spawned closer than this to the player
+const CHASER_MIN_PLAYER_DIST: f32 = 200.0;
+
+/// Runs a benchmark scenario with `--bench <file>` and writes a report when it's done
+pub struct BenchPlugin;
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Startup,
+            start_benchmark.before(start_playback).before(load_tilemap),
+        )
+        .add_systems(
+            PreUpdate,
+            script_input
+                .run_if(resource_exists::<Benchmark>)
+                .in_set(PlayerInputSet)
+                .after(read_player_action),
+        )
+        .add_systems(Update, spawn_chasers.run_if(resource_exists::<Benchmark>))
+        // Measured spans. Each is timed by systems ordered right around it, so they include a
+        // little scheduling overhead. The fixed schedules run single-threaded while benchmarking so
+        // nothing else runs at the same time as a span.
+        .add_systems(
+            FixedUpdate,
+            (
+                start_span(Span::UpdateCost)
+                    .after(update_target)
+                    .before(update_cost),
+                end_span(Span::UpdateCost).after(update_cost),
+                start_span(Span::ApplyForce)
+                    .after(update_cost)
+                    .before(apply_force),
+                end_span(Span::ApplyForce).after(apply_force),
+                start_span(Span::ChaserAvoidance)
+                    .after(apply_force)
+                    .before(update_chaser_avoidance),
+                end_span(Span::ChaserAvoidance).after(update_chaser_avoidance),
+            )
+                .run_if(resource_exists::<Benchmark>),
+        )
+        // Rapier steps in `FixedPostUpdate`
+        .add_systems(
+            FixedPostUpdate,
+            (
+                start_span(Span::PhysicsStep)
+                    .after(PhysicsSet::SyncBackend)
+                    .before(PhysicsSet::StepSimulation),
+                end_span(Span::PhysicsStep)
+                    .after(PhysicsSet::StepSimulation)
+                    .before(PhysicsSet::Writeback),
+            )
+                .run_if(resource_exists::<Benchmark>),
+        )
+        .add_systems(
+            Last,
+            finish_frame
+                .run_if(resource_exists::<Benchmark>)
+                .after(advance_playback),
+        );
+    }
+}
+
+/// Benchmark scenario read from a RON file
+#[derive(Deserialize, Debug)]
+pub struct Scenario {
+    /// Map filename in `assets/levels/`
+    pub map: String,
+    #[serde(default)]
+    pub seed: u64,
+    /// Chasers spawned on random free tiles on the first frame
+    #[serde(default)]
+    pub chasers: u32,
+    /// Frames to run for
+    pub frames: u32,
+    /// Frames at the start that are left out of the results
+    #[serde(default)]
+    pub warmup: u32,
+    pub input: ScenarioInput,
+}
+
+#[derive(Deserialize, Debug)]
+pub enum ScenarioInput {
+    /// Hold each action for its number of frames. The last one is held until the end.
+    Script(Vec<InputStep>),
+    /// Play back a replay file. Its map and seed are used instead of the scenario's.
+    Replay(PathBuf),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InputStep {
+    pub frames: u32,
+    pub action: PlayerAction,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Self {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read scenario {:?}: {e}", path));
+        ron::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse scenario {:?}: {e}", path))
+    }
+
+    /// Scripted action for a frame
+    fn action(&self, frame: u32) -> PlayerAction {
+        let ScenarioInput::Script(steps) = &self.input else {
+            return PlayerAction::default();
+        };
+        let mut start = 0;
+        for step in steps {
+            start += step.frames;
+            if frame < start {
+                return step.action;
+            }
+        }
+        steps
+            .last()
+            .map_or_else(PlayerAction::default, |step| step.action)
+    }
+}
+
+/// Timed parts of a frame
+#[derive(Clone, Copy, Debug)]
+enum Span {
+    UpdateCost,
+    ApplyForce,
+    ChaserAvoidance,
+    PhysicsStep,
+}
+
+#[derive(Default, Debug)]
+struct SpanTimer {
+    start: Option<Instant>,
+    /// Time spent during measured frames
+    total: Duration,
+}
+
+#[derive(Resource, Debug)]
+struct Benchmark {
+    name: String,
+    output: PathBuf,
+    scenario: Scenario,
+    frame: u32,
+    last_frame: Option<Instant>,
+    /// Milliseconds of every measured frame
+    frame_times: Vec<f64>,
+    spans: [SpanTimer; 4],
+    max_chasers: usize,
+}
+
+impl Benchmark {
+    fn measuring(&self) -> bool {
+        self.frame >= self.scenario.warmup
+    }
+}
+
+/// Results written when the scenario is done. All times are in milliseconds.
+#[derive(Serialize, Debug)]
+struct Report {
+    scenario: String,
+    map: String,
+    seed: u64,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+    frames: usize,
+    frame_time_mean: f64,
+    frame_time_p50: f64,
+    frame_time_p90: f64,
+    frame_time_p99: f64,
+    frame_time_max: f64,
+    /// Mean time per frame spent in each span
+    update_cost: f64,
+    apply_force: f64,
+    chaser_avoidance: f64,
+    physics_step: f64,
+    entities: usize,
+    rigid_bodies: usize,
+    chasers: usize,
+    max_chasers: usize,
+}
+
+impl Report {
+    const CSV_HEADER: &'static str = "scenario,map,seed,timestamp,frames,frame_time_mean,\
+        frame_time_p50,frame_time_p90,frame_time_p99,frame_time_max,update_cost,apply_force,\
+        chaser_avoidance,physics_step,entities,rigid_bodies,chasers,max_chasers";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{},{},{},{}",
+            self.scenario,
+            self.map,
+            self.seed,
+            self.timestamp,
+            self.frames,
+            self.frame_time_mean,
+            self.frame_time_p50,
+            self.frame_time_p90,
+            self.frame_time_p99,
+            self.frame_time_max,
+            self.update_cost,
+            self.apply_force,
+            self.chaser_avoidance,
+            self.physics_step,
+            self.entities,
+            self.rigid_bodies,
+            self.chasers,
+            self.max_chasers,
+        )
+    }
+
+    /// CSV files get a row appended so runs of different commits can be compared. Anything
+    /// else is overwritten with JSON.
+    fn write(&self, path: &Path) -> std::io::Result<()> {
+        if path.extension().is_some_and(|ext| ext == "csv") {
+            let new_file = !path.exists();
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            if new_file {
+                writeln!(file, "{}", Self::CSV_HEADER)?;
+            }
+            writeln!(file, "{}", self.csv_row())
+        } else {
+            std::fs::write(path, serde_json::to_string_pretty(self)?)
+        }
+    }
+}
+
+/// Value below which `p` of the sorted samples fall
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+/// Load the scenario and set up its level, seed and input
+fn start_benchmark(
+    mut commands: Commands,
+    mut args: ResMut<Args>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut schedules: ResMut<Schedules>,
+) {
+    let Some(path) = args.bench.clone() else {
+        return;
+    };
+    let scenario = Scenario::load(&path);
+    let name = path
+        .file_stem()
+        .map_or_else(|| "bench".to_string(), |s| s.to_string_lossy().into_owned());
+    info!("Running benchmark {name} for {} frames", scenario.frames);
+
+    args.map = Some(scenario.map.clone());
+    args.seed = Some(scenario.seed);
+    // The replay sets up its own map, seed and frame times
+    if let ScenarioInput::Replay(replay) = &scenario.input {
+        args.replay = Some(replay.clone());
+    }
+    // Same simulated time every frame, so every run does the same work
+    *time_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(BENCH_FRAME_TIME));
+    // Spans are timed from the outside, which only works if their systems run one at a time
+    if let Some(schedule) = schedules.get_mut(FixedUpdate) {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+    if let Some(schedule) = schedules.get_mut(FixedPostUpdate) {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+
+    let output = args
+        .bench_out
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("bench-{name}.json")));
+    commands.insert_resource(Benchmark {
+        name,
+        output,
+        scenario,
+        frame: 0,
+        last_frame: None,
+        frame_times: vec![],
+        spans: default(),
+        max_chasers: 0,
+    });
+}
+
+fn script_input(bench: Res<Benchmark>, mut action: ResMut<PlayerAction>) {
+    if let ScenarioInput::Script(_) = bench.scenario.input {
+        *action = bench.scenario.action(bench.frame);
+    }
+}
+
+/// Spawn the scenario's chasers on random free tiles on the first frame
+fn spawn_chasers(
+    mut commands: Commands,
+    bench: Res<Benchmark>,
+    mut rng: ResMut<GameRng>,
+    chaser_assets: Res<ChaserAssets>,
+    shooter_assets: Res<ShooterAssets>,
+    q_map: Query<(&Tilemap, &TileStorage)>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    if bench.frame != 0 || bench.scenario.chasers == 0 {
+        return;
+    }
+    let (Ok((map, storage)), Ok(player_transform)) = (q_map.get_single(), q_player.get_single())
+    else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+    // Drawn below the player
+    let z = player_transform.translation.z - 1.0;
+
+    let free_tiles = (0..map.height as u32)
+        .flat_map(|y| (0..map.width as u32).map(move |x| (x, y)))
+        .map(|tile| map.tile_to_world_coords(tile).as_vec2())
+        .filter(|&pos| {
+            !storage.is_wall(map, pos) && pos.distance(player_pos) >= CHASER_MIN_PLAYER_DIST
+        })
+        .collect::<Vec<_>>();
+    if free_tiles.is_empty() {
+        warn!("No free tiles to spawn chasers on");
+        return;
+    }
+    for _ in 0..bench.scenario.chasers {
+        let pos = free_tiles[rng.gen_range(0..free_tiles.len())];
+        spawn_enemy(
+            &mut commands,
+            &chaser_assets,
+            &shooter_assets,
+            &mut rng,
+            pos.extend(z),
+            EnemyKind::Chaser,
+            None,
+        );
+    }
+}
+
+fn start_span(span: Span) -> impl FnMut(ResMut<Benchmark>) {
+    move |mut bench| {
+        bench.spans[span as usize].start = Some(Instant::now());
+    }
+}
+
+fn end_span(span: Span) -> impl FnMut(ResMut<Benchmark>) {
+    move |mut bench| {
+        let measuring = bench.measuring();
+        let timer = &mut bench.spans[span as usize];
+        if let Some(start) = timer.start.take() {
+            if measuring {
+                timer.total += start.elapsed();
+            }
+        }
+    }
+}
+
+/// Record the frame time. Writes the report and exits once the scenario is over or the app
+/// exits early.
+fn finish_frame(
+    mut bench: ResMut<Benchmark>,
+    rng: Res<GameRng>,
+    args: Res<Args>,
+    q_entities: Query<Entity>,
+    q_bodies: Query<(), With<RigidBody>>,
+    q_chasers: Query<(), With<Chaser>>,
+    mut exit_events: ResMut<Events<AppExit>>,
+) {
+    let now = Instant::now();
+    if let Some(last_frame) = bench.last_frame.replace(now) {
+        if bench.measuring() {
+            let frame_time = (now - last_frame).as_secs_f64() * 1000.0;
+            bench.frame_times.push(frame_time);
+        }
+    }
+    let chasers = q_chasers.iter().count();
+    bench.max_chasers = bench.max_chasers.max(chasers);
+    bench.frame += 1;
+
+    let exiting = !exit_events.is_empty();
+    if bench.frame < bench.scenario.frames && !exiting {
+        return;
+    }
+
+    let mut sorted = bench.frame_times.clone();
+    sorted.sort_by(f64::total_cmp);
+    let frames = sorted.len();
+    let per_frame =
+        |span: Span| bench.spans[span as usize].total.as_secs_f64() * 1000.0 / frames.max(1) as f64;
+    let report = Report {
+        scenario: bench.name.clone(),
+        map: args.map.clone().unwrap_or_default(),
+        seed: rng.seed(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        frames,
+        frame_time_mean: sorted.iter().sum::<f64>() / frames.max(1) as f64,
+        frame_time_p50: percentile(&sorted, 0.5),
+        frame_time_p90: percentile(&sorted, 0.9),
+        frame_time_p99: percentile(&sorted, 0.99),
+        frame_time_max: sorted.last().copied().unwrap_or(0.0),
+        update_cost: per_frame(Span::UpdateCost),
+        apply_force: per_frame(Span::ApplyForce),
+        chaser_avoidance: per_frame(Span::ChaserAvoidance),
+        physics_step: per_frame(Span::PhysicsStep),
+        entities: q_entities.iter().count(),
+        rigid_bodies: q_bodies.iter().count(),
+        chasers,
+        max_chasers: bench.max_chasers,
+    };
+    match report.write(&bench.output) {
+        Ok(()) => info!("Benchmark results written to {:?}", bench.output),
+        Err(e) => error!("Failed to write benchmark results {:?}: {e}", bench.output),
+    }
+    info!(
+        "{frames} frames, mean {:.2} ms, p99 {:.2} ms",
+        report.frame_time_mean, report.frame_time_p99
+    );
+    if !exiting {
+        exit_events.send(AppExit::Success);
+    }
+}
@@ -0,0 +1,552 @@
+use bevy::{
+    color::palettes::css::{DARK_RED, LIME, ORANGE_RED},
+    math::vec2,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    time::Stopwatch,
+};
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use std::{f32::consts::PI, path::PathBuf};
+use tiled::ObjectData;
+
+use crate::{
+    chaser::CHASER_RADIUS,
+    config::Config,
+    flowfield::Flowfield,
+    physics::{SimulationSet, CHASER_GROUP},
+    player::{Player, PlayerHitEvent, ResetEvent},
+    properties::CustomProperties,
+    spawner::{SpawnArea, Spawner},
+    tilemap::{TileStorage, Tilemap},
+};
+
+const BOSS_COLOR: Color = Color::srgb(0.85, 0.15, 0.1);
+const BOSS_DAMPING: f32 = 5.0;
+const BOSS_Z: f32 = 4.0;
+/// Seconds the boss can't be damaged after being hit
+const BOSS_INVULNERABLE_TIME: f32 = 0.5;
+/// Width of the ring around the boss that its waves spawn in, relative to its radius
+const SPAWN_RING_WIDTH: f32 = 1.0;
+/// Boss scripts are loaded from here
+const BOSS_DIR: &str = "assets/bosses";
+
+/// Bosses with data-driven phase scripts
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                prepare_bosses,
+                damage_bosses,
+                run_boss_scripts,
+                update_hazards,
+                reset_bosses,
+            )
+                .chain()
+                .in_set(SimulationSet),
+        )
+        .add_systems(Update, draw_boss_health);
+    }
+}
+
+/// Boss definition loaded from a RON file in `assets/bosses/`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossScript {
+    pub name: String,
+    pub health: u32,
+    /// Radius of the main body. Smaller parts are scaled from this.
+    pub radius: f32,
+    pub phases: Vec<BossPhase>,
+}
+
+impl BossScript {
+    pub fn load(filename: &str) -> Self {
+        let path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join(BOSS_DIR)
+            .join(filename);
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read boss script {:?}: {e}", path));
+        let script: BossScript = ron::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse boss script {:?}: {e}", path));
+        assert!(
+            !script.phases.is_empty(),
+            "Boss script {:?} has no phases",
+            path
+        );
+        // A spawner with 0 to spawn never finishes
+        for behavior in script.phases.iter().flat_map(|phase| &phase.behaviors) {
+            if let BossBehavior::SpawnWave { count, .. } = *behavior {
+                assert!(
+                    count > 0,
+                    "Boss script {:?} has a SpawnWave with count {count}. It must be at least 1.",
+                    path
+                );
+            }
+        }
+        script
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPhase {
+    pub until: PhaseEnd,
+    /// All behaviors run at the same time
+    pub behaviors: Vec<BossBehavior>,
+}
+
+/// When to move on to the next phase. The last phase lasts until the boss is defeated.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum PhaseEnd {
+    /// Seconds since the phase started
+    Time(f32),
+    /// Health at or below this value
+    Health(u32),
+    Never,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum BossBehavior {
+    /// Follow the flowfield towards the player
+    Chase { speed: f32 },
+    /// Burst of speed along the flowfield
+    Charge {
+        speed: f32,
+        duration: f32,
+        cooldown: f32,
+    },
+    /// Spawn `count` chasers around the boss. `count` must be at least 1.
+    SpawnWave {
+        count: i32,
+        interval: f32,
+        cooldown: f32,
+    },
+    /// Mark a zone at the player position that hurts after `warmup` seconds
+    AreaDenial {
+        radius: f32,
+        warmup: f32,
+        duration: f32,
+        cooldown: f32,
+    },
+}
+
+impl BossBehavior {
+    fn cooldown(&self) -> f32 {
+        match *self {
+            BossBehavior::Chase { .. } => 0.0,
+            BossBehavior::Charge { cooldown, .. }
+            | BossBehavior::SpawnWave { cooldown, .. }
+            | BossBehavior::AreaDenial { cooldown, .. } => cooldown,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Charge {
+    dir: Dir2,
+    speed: f32,
+    timer: Timer,
+}
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    script: BossScript,
+    /// Where the boss was placed in the level
+    home: Vec2,
+    pub health: u32,
+    phase: usize,
+    phase_time: Stopwatch,
+    /// One per behavior in the current phase
+    cooldowns: Vec<Timer>,
+    charge: Option<Charge>,
+    invulnerable: Timer,
+    defeated: bool,
+}
+
+impl Boss {
+    /// `home` is the object position in world coords.
+    pub fn from_object(object_data: &ObjectData, home: Vec2) -> Self {
+        let script = object_data
+            .get_property::<String>("script")
+            .unwrap_or_else(|e| panic!("{e}"));
+        let script = BossScript::load(&script);
+        let mut boss = Boss {
+            health: script.health,
+            script,
+            home,
+            phase: 0,
+            phase_time: Stopwatch::new(),
+            cooldowns: vec![],
+            charge: None,
+            invulnerable: Timer::from_seconds(BOSS_INVULNERABLE_TIME, TimerMode::Once),
+            defeated: false,
+        };
+        boss.start_phase(0);
+        boss
+    }
+
+    fn start_phase(&mut self, phase: usize) {
+        self.phase = phase;
+        self.phase_time.reset();
+        self.charge = None;
+        self.cooldowns = self.script.phases[phase]
+            .behaviors
+            .iter()
+            .map(|b| Timer::from_seconds(b.cooldown(), TimerMode::Repeating))
+            .collect();
+    }
+
+    fn phase_finished(&self) -> bool {
+        if self.phase + 1 >= self.script.phases.len() {
+            return false;
+        }
+        match self.script.phases[self.phase].until {
+            PhaseEnd::Time(secs) => self.phase_time.elapsed_secs() >= secs,
+            PhaseEnd::Health(health) => self.health <= health,
+            PhaseEnd::Never => false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.health = self.script.health;
+        self.defeated = false;
+        self.invulnerable.tick(self.invulnerable.duration());
+        self.start_phase(0);
+    }
+}
+
+/// Zone that hurts the player after warming up.
+#[derive(Component, Debug)]
+struct Hazard {
+    radius: f32,
+    warmup: Timer,
+    lifetime: Timer,
+}
+
+/// Add physics, visuals and a spawner to bosses loaded from the tilemap
+fn prepare_bosses(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_bosses: Query<(Entity, &Boss, &Transform), Added<Boss>>,
+) {
+    for (entity, boss, transform) in q_bosses.iter() {
+        let r = boss.script.radius;
+        // Main body with a smaller part on each side
+        let parts = [
+            (Vec2::ZERO, r),
+            (vec2(-r, -r * 0.4), r * 0.5),
+            (vec2(r, -r * 0.4), r * 0.5),
+        ];
+        let material = materials.add(ColorMaterial::from_color(BOSS_COLOR));
+        // Waves spawn in a ring clear of every part
+        let extent = parts
+            .iter()
+            .map(|&(offset, radius)| offset.length() + radius)
+            .fold(0.0, f32::max);
+        let inner_radius = extent + CHASER_RADIUS;
+
+        commands
+            .entity(entity)
+            .insert((
+                MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(Circle::new(r))),
+                    material: material.clone(),
+                    transform: transform.with_translation(transform.translation.with_z(BOSS_Z)),
+                    ..default()
+                },
+                RigidBody::Dynamic,
+                Collider::compound(
+                    parts
+                        .iter()
+                        .map(|&(offset, radius)| (offset, 0.0, Collider::ball(radius)))
+                        .collect(),
+                ),
+                ColliderMassProperties::Mass(500.0),
+                CollisionGroups::new(CHASER_GROUP, Group::ALL),
+                LockedAxes::ROTATION_LOCKED,
+                Damping {
+                    linear_damping: BOSS_DAMPING,
+                    angular_damping: 0.0,
+                },
+                ExternalForce::default(),
+                Velocity::default(),
+                ReadMassProperties::default(),
+                Spawner {
+                    area: SpawnArea::Ring { inner_radius },
+                    scatter_radius: inner_radius + r * SPAWN_RING_WIDTH,
+                    ..default()
+                },
+                Name::new(boss.script.name.clone()),
+            ))
+            .with_children(|parent| {
+                for &(offset, radius) in parts.iter().skip(1) {
+                    parent.spawn(MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(meshes.add(Circle::new(radius))),
+                        material: material.clone(),
+                        transform: Transform::from_translation(offset.extend(-0.1)),
+                        ..default()
+                    });
+                }
+            });
+    }
+}
+
+/// Boosting into the boss damages it. Otherwise the boss hurts the player.
+fn damage_bosses(
+    time: Res<Time>,
+    mut contact_events: EventReader<ContactForceEvent>,
+    q_player: Query<(Entity, &Velocity, &Player)>,
+    mut q_bosses: Query<&mut Boss>,
+    mut hit_events: EventWriter<PlayerHitEvent>,
+) {
+    for mut boss in q_bosses.iter_mut() {
+        boss.invulnerable.tick(time.delta());
+    }
+    let Ok((player_entity, velocity, player)) = q_player.get_single() else {
+        return;
+    };
+
+    for event in contact_events.read() {
+        let boss_entity = if event.collider1 == player_entity {
+            event.collider2
+        } else if event.collider2 == player_entity {
+            event.collider1
+        } else {
+            continue;
+        };
+        let Ok(mut boss) = q_bosses.get_mut(boss_entity) else {
+            continue;
+        };
+        if boss.defeated {
+            continue;
+        }
+
+        if !player.is_boosting(velocity.linvel.length()) {
+            hit_events.send(PlayerHitEvent);
+            continue;
+        }
+        if !boss.invulnerable.finished() {
+            continue;
+        }
+        boss.health = boss.health.saturating_sub(1);
+        boss.invulnerable.reset();
+    }
+}
+
+/// Advance phases and run the behaviors of the current phase.
+fn run_boss_scripts(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<Config>,
+    mut q_flowfield: Query<&mut Flowfield>,
+    q_map: Query<&Tilemap>,
+    q_tile_storage: Query<&TileStorage>,
+    q_player: Query<&Transform, (With<Player>, Without<Boss>)>,
+    mut q_bosses: Query<(
+        Entity,
+        &mut Boss,
+        &mut Spawner,
+        &mut ExternalForce,
+        &mut Transform,
+        &ReadMassProperties,
+    )>,
+) {
+    let (Ok(map), Ok(storage), Ok(player_transform)) = (
+        q_map.get_single(),
+        q_tile_storage.get_single(),
+        q_player.get_single(),
+    ) else {
+        return;
+    };
+    let mut flowfield = q_flowfield.single_mut();
+    let player_pos = player_transform.translation.xy();
+
+    for (entity, mut boss, mut spawner, mut force, mut transform, read_mass) in q_bosses.iter_mut()
+    {
+        if boss.defeated {
+            continue;
+        }
+        if boss.health == 0 {
+            info!("{} defeated!", boss.script.name);
+            boss.defeated = true;
+            spawner.active = false;
+            force.force = Vec2::ZERO;
+            commands.entity(entity).insert((
+                RigidBodyDisabled,
+                ColliderDisabled,
+                Visibility::Hidden,
+            ));
+            continue;
+        }
+
+        boss.phase_time.tick(time.delta());
+        if boss.phase_finished() {
+            let next = boss.phase + 1;
+            boss.start_phase(next);
+        }
+
+        let pos = transform.translation.xy();
+        let tile_coords = map.world_to_tile_coords(&pos);
+        let flow = flowfield.get_flow_at_tile(tile_coords, storage, config.flowfield_smooth);
+        // Force needed for linear damping to cap velocity at `speed`
+        let force_for_speed = |speed: f32| speed * BOSS_DAMPING * read_mass.mass;
+
+        force.force = Vec2::ZERO;
+        let boss = &mut *boss;
+        let behaviors = &boss.script.phases[boss.phase].behaviors;
+
+        for (behavior, cooldown) in behaviors.iter().zip(boss.cooldowns.iter_mut()) {
+            if let BossBehavior::Chase { speed } = *behavior {
+                force.force = flow * force_for_speed(speed);
+                continue;
+            }
+            cooldown.tick(time.delta());
+            if !cooldown.just_finished() {
+                continue;
+            }
+
+            match *behavior {
+                BossBehavior::Chase { .. } => (),
+                BossBehavior::Charge {
+                    speed, duration, ..
+                } => {
+                    boss.charge = Some(Charge {
+                        dir: flow,
+                        speed,
+                        timer: Timer::from_seconds(duration, TimerMode::Once),
+                    });
+                }
+                BossBehavior::SpawnWave {
+                    count, interval, ..
+                } => {
+                    spawner.num_spawn = count;
+                    spawner.interval = interval;
+                    spawner.delay = interval;
+                    spawner.repeats = false;
+                    spawner.count = 0;
+                    spawner.timer = Timer::from_seconds(0.0, TimerMode::Repeating);
+                    spawner.active = true;
+                }
+                BossBehavior::AreaDenial {
+                    radius,
+                    warmup,
+                    duration,
+                    ..
+                } => {
+                    commands.spawn((
+                        Hazard {
+                            radius,
+                            warmup: Timer::from_seconds(warmup, TimerMode::Once),
+                            lifetime: Timer::from_seconds(warmup + duration, TimerMode::Once),
+                        },
+                        TransformBundle::from_transform(Transform::from_translation(
+                            player_pos.extend(0.0),
+                        )),
+                        Name::new("Hazard"),
+                    ));
+                }
+            }
+        }
+
+        // Charging overrides other movement
+        if let Some(charge) = boss.charge.as_mut() {
+            charge.timer.tick(time.delta());
+            force.force = charge.dir * force_for_speed(charge.speed);
+            if charge.timer.finished() {
+                boss.charge = None;
+            }
+        }
+
+        // Face the direction of travel
+        if let Ok(dir) = Dir2::new(force.force) {
+            let target = Quat::from_rotation_z(dir.to_angle() - 0.5 * PI);
+            let s = 4.0 * time.delta_seconds();
+            transform.rotation = transform.rotation.lerp(target, s);
+        }
+    }
+}
+
+/// Hurt the player while they are inside an active hazard.
+fn update_hazards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    q_player: Query<&Transform, With<Player>>,
+    mut q_hazards: Query<(Entity, &mut Hazard, &Transform)>,
+    mut hit_events: EventWriter<PlayerHitEvent>,
+) {
+    let player_pos = q_player.get_single().ok().map(|t| t.translation.xy());
+
+    for (entity, mut hazard, transform) in q_hazards.iter_mut() {
+        hazard.warmup.tick(time.delta());
+        hazard.lifetime.tick(time.delta());
+        if hazard.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let pos = transform.translation.xy();
+
+        if !hazard.warmup.finished() {
+            // Telegraph the hazard with a growing circle
+            let progress = hazard.warmup.fraction();
+            gizmos.circle_2d(pos, hazard.radius, ORANGE_RED.with_alpha(0.4));
+            gizmos.circle_2d(pos, hazard.radius * progress, ORANGE_RED.with_alpha(0.4));
+            continue;
+        }
+        gizmos.circle_2d(pos, hazard.radius, ORANGE_RED);
+        gizmos.circle_2d(pos, hazard.radius * 0.95, ORANGE_RED);
+
+        if player_pos.is_some_and(|p| p.distance(pos) < hazard.radius) {
+            hit_events.send(PlayerHitEvent);
+        }
+    }
+}
+
+/// Health bar above each boss
+fn draw_boss_health(mut gizmos: Gizmos, q_bosses: Query<(&Boss, &Transform)>) {
+    for (boss, transform) in q_bosses.iter() {
+        if boss.defeated {
+            continue;
+        }
+        let width = boss.script.radius * 3.0;
+        let start = transform.translation.xy() + vec2(-width * 0.5, boss.script.radius * 1.6);
+        let fraction = boss.health as f32 / boss.script.health as f32;
+
+        gizmos.line_2d(start, start + Vec2::X * width, DARK_RED);
+        gizmos.line_2d(start, start + Vec2::X * width * fraction, LIME);
+    }
+}
+
+/// Restore bosses to full health at their starting position
+fn reset_bosses(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    mut q_bosses: Query<(
+        Entity,
+        &mut Boss,
+        &mut Spawner,
+        &mut Transform,
+        &mut Velocity,
+    )>,
+    q_hazards: Query<Entity, With<Hazard>>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for (entity, mut boss, mut spawner, mut transform, mut velocity) in q_bosses.iter_mut() {
+        boss.reset();
+        spawner.active = false;
+        transform.translation = boss.home.extend(BOSS_Z);
+        velocity.linvel = Vec2::ZERO;
+        commands
+            .entity(entity)
+            .remove::<(RigidBodyDisabled, ColliderDisabled)>()
+            .insert(Visibility::Visible);
+    }
+    for entity in q_hazards.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -3,9 +3,8 @@ use bevy::{
     render::camera::ScalingMode,
     window::{WindowResized, WindowResolution},
 };
-use bevy_rapier2d::plugin::PhysicsSet;
 
-use crate::{config::Config, player::Player, tilemap::Tilemap};
+use crate::{config::Config, physics::interpolate_transforms, player::Player, tilemap::Tilemap};
 
 /// Plugin that spawns the camera, allows zooming in/out, and has the camera follow the player
 pub struct CameraPlugin;
@@ -13,12 +12,12 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_camera)
-            // To eliminate jittery camera movement these systems must run AFTER physics and BEFORE transform propogation
+            // To eliminate jittery camera movement these systems must run AFTER interpolation and BEFORE transform propogation
             .add_systems(
                 PostUpdate,
                 (update_range, camera_follow_player)
                     .chain()
-                    .after(PhysicsSet::SyncBackend)
+                    .after(interpolate_transforms)
                     .before(TransformSystem::TransformPropagate),
             )
             .add_systems(Update, camera_zoom)
@@ -74,6 +73,13 @@ fn update_range(
 #[derive(Component)]
 pub struct MainCamera;
 
+/// Added to the main camera to follow `target` instead of the player until `timer` finishes
+#[derive(Component, Debug)]
+pub struct CameraFocus {
+    pub target: Vec2,
+    pub timer: Timer,
+}
+
 fn spawn_camera(mut commands: Commands, q_window: Query<&Window>) {
     let mut camera = Camera2dBundle::default();
 
@@ -134,9 +140,19 @@ fn camera_zoom(
     }
 }
 
-/// Adjust camera position to track player
+/// Adjust camera position to track player or the current [`CameraFocus`]
 fn camera_follow_player(
-    mut q_camera: Query<(&mut Transform, &CameraRange, &OrthographicProjection), With<MainCamera>>,
+    mut commands: Commands,
+    mut q_camera: Query<
+        (
+            Entity,
+            &mut Transform,
+            &CameraRange,
+            &OrthographicProjection,
+            Option<&mut CameraFocus>,
+        ),
+        With<MainCamera>,
+    >,
     q_player: Query<(&Transform, &Player), Without<MainCamera>>,
     time: Res<Time>,
     config: Res<Config>,
@@ -153,11 +169,22 @@ fn camera_follow_player(
         return;
     };
     // Get camera info
-    let (mut transform, range, ortho) = q_camera.single_mut();
+    let (entity, mut transform, range, ortho, focus) = q_camera.single_mut();
     let translation = &mut transform.translation;
 
-    // Camera speed will depend on how far away the player is
-    let mut diff = (player_transform.translation - *translation).xy();
+    // Follow the focus target instead of the player until its timer runs out
+    let mut target = player_transform.translation.xy();
+    if let Some(mut focus) = focus {
+        focus.timer.tick(time.delta());
+        if focus.timer.finished() {
+            commands.entity(entity).remove::<CameraFocus>();
+        } else {
+            target = focus.target;
+        }
+    }
+
+    // Camera speed will depend on how far away the target is
+    let mut diff = target - translation.xy();
     // Prevents camera movement if player is within `MIN_DIFF` distance of camera
     diff = (diff.abs() - MIN_DIFF).max(Vec2::ZERO).copysign(diff);
 
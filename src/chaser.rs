@@ -5,7 +5,7 @@ use bevy_svg::prelude::*;
 use crate::{
     config::Config,
     flowfield::apply_force,
-    physics::{CHASER_GROUP, WALL_GROUP},
+    physics::{SimulationSet, CHASER_GROUP, WALL_GROUP},
     player::Player,
 };
 
@@ -21,8 +21,12 @@ pub struct ChaserPlugin;
 
 impl Plugin for ChaserPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
-            .add_systems(Update, update_chaser_avoidance.after(apply_force));
+        app.add_systems(Startup, setup).add_systems(
+            FixedUpdate,
+            update_chaser_avoidance
+                .after(apply_force)
+                .in_set(SimulationSet),
+        );
         // .add_systems(Update, update_chaser_velocity);
     }
 }
@@ -138,7 +142,7 @@ fn chasers_follow_player(
     }
 }
 
-fn update_chaser_avoidance(
+pub fn update_chaser_avoidance(
     mut query_chasers: Query<(&mut ExternalForce, &Transform), With<Chaser>>,
     config: Res<Config>,
     rapier_context: Res<RapierContext>,
@@ -1,6 +1,30 @@
-use bevy::{input::common_conditions::input_toggle_active, prelude::*, window::PrimaryWindow};
+use bevy::{
+    input::common_conditions::input_toggle_active,
+    prelude::*,
+    reflect::{
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        TypeInfo, TypeRegistry, Typed,
+    },
+    window::PrimaryWindow,
+};
 use bevy_egui::egui::{self, CollapsingHeader, RichText};
-use bevy_inspector_egui::{bevy_egui::EguiContext, bevy_inspector::ui_for_resource, prelude::*};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext,
+    bevy_inspector::{ui_for_entity, ui_for_resource},
+    prelude::*,
+};
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeSeed, Serialize};
+use std::{
+    any::TypeId,
+    path::{Path, PathBuf},
+};
+
+use crate::{player::Player, tilemap::Args};
+
+/// Directory inside the platform config directory
+const SETTINGS_DIR: &str = "train_game";
+const SETTINGS_FILE: &str = "settings.ron";
 
 pub struct ConfigPlugin;
 
@@ -14,6 +38,8 @@ impl Plugin for ConfigPlugin {
             .register_type::<Config>()
             .init_resource::<DebugViews>()
             .register_type::<DebugViews>()
+            // Before anything reads the config
+            .add_systems(PreStartup, load_settings)
             // .add_plugins(
             //     ResourceInspectorPlugin::<Config>::default()
             //         .run_if(input_toggle_active(false, KeyCode::KeyC)),
@@ -72,6 +98,34 @@ pub struct Config {
     pub chaser_avoidance_max: f32,
     #[inspector(min = 0.0, max = 10.0, speed = 0.01)]
     pub chaser_rng_force: f32,
+    /// Max number of chasers that can couple into a train. Below 2 turns trains off.
+    #[inspector(min = 0, max = 100)]
+    pub max_train_length: usize,
+    /// Shooters back away from the player below this flowfield cost
+    #[inspector(min = 0, max = 2000)]
+    pub shooter_cost_min: u32,
+    /// Shooters approach the player above this flowfield cost
+    #[inspector(min = 0, max = 2000)]
+    pub shooter_cost_max: u32,
+    /// Enemies don't spawn closer than this to the player
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub spawn_min_player_dist: f32,
+    /// Enemies don't spawn where the flowfield cost to the player is below this
+    #[inspector(min = 0, max = 2000)]
+    pub spawn_min_player_cost: u32,
+    /// Seconds a warning marker is shown before an enemy appears. 0 spawns immediately.
+    #[inspector(min = 0.0, max = 5.0)]
+    pub spawn_telegraph_time: f32,
+    /// Hits the player can take before the level restarts. 0 means unlimited.
+    #[inspector(min = 0, max = 99)]
+    pub player_lives: u32,
+    /// Seconds after losing a life in which hits are ignored
+    #[inspector(min = 0.0, max = 10.0)]
+    pub player_invulnerable_time: f32,
+    /// Chasers that get this close to the player's center and leave without touching it are
+    /// near-misses
+    #[inspector(min = 0.0, max = 200.0)]
+    pub near_miss_radius: f32,
 
     #[inspector(min = 0.0, max = 0.1, speed = 0.0001)]
     pub stick_deadzone: f32,
@@ -95,6 +149,15 @@ impl Default for Config {
             chaser_avoidance_mul: 3_200_000.0,
             chaser_avoidance_max: 30_000.0,
             chaser_rng_force: 0.4,
+            max_train_length: 8,
+            shooter_cost_min: 60,
+            shooter_cost_max: 120,
+            spawn_min_player_dist: 96.0,
+            spawn_min_player_cost: 60,
+            spawn_telegraph_time: 0.6,
+            player_lives: 0,
+            player_invulnerable_time: 1.5,
+            near_miss_radius: 28.0,
             stick_deadzone: 0.07460,
             camera_follow_dist: 125.0,
             framerate: 0.0,
@@ -103,6 +166,116 @@ impl Default for Config {
     }
 }
 
+/// Where the config window saves `Config` and `DebugViews`
+#[derive(Resource, Debug)]
+struct SettingsPath(PathBuf);
+
+/// `Config` and `DebugViews` as they are written to the settings file
+#[derive(Serialize)]
+struct SettingsFile<'a> {
+    config: TypedReflectSerializer<'a>,
+    debug_views: TypedReflectSerializer<'a>,
+}
+
+/// Load settings from `--config`, or from the config directory. Headless runs, benchmarks and
+/// replays only use `--config` so they play out the same on every machine.
+fn load_settings(world: &mut World) {
+    let (config_arg, uses_defaults) = world.get_resource::<Args>().map_or((None, true), |args| {
+        let uses_defaults = args.headless || args.bench.is_some() || args.replay.is_some();
+        (args.config.clone(), uses_defaults)
+    });
+    let default_path = dirs::config_dir()
+        .unwrap_or_default()
+        .join(SETTINGS_DIR)
+        .join(SETTINGS_FILE);
+    let path = config_arg.clone().unwrap_or(default_path);
+    world.insert_resource(SettingsPath(path.clone()));
+
+    if config_arg.is_none() && (uses_defaults || !path.exists()) {
+        return;
+    }
+    match read_settings(world, &path) {
+        Ok(()) => info!("Loaded settings from {:?}", path),
+        Err(e) => error!("Failed to load settings {:?}: {e}", path),
+    }
+}
+
+fn read_settings(world: &mut World, path: &Path) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let settings = ron::from_str(&contents).map_err(|e| e.to_string())?;
+    let ron::Value::Map(mut settings) = settings else {
+        return Err("expected `(config: (..), debug_views: (..))`".to_string());
+    };
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    if let Some(value) = settings.remove(&ron::Value::String("config".to_string())) {
+        apply_settings(&mut *world.resource_mut::<Config>(), &registry, value)?;
+    }
+    if let Some(value) = settings.remove(&ron::Value::String("debug_views".to_string())) {
+        apply_settings(&mut *world.resource_mut::<DebugViews>(), &registry, value)?;
+    }
+    Ok(())
+}
+
+/// Fields of `settings` as a RON value, like they are written to the settings file. Replays
+/// store the `Config` they were recorded with this way.
+pub fn settings_value<R: Reflect>(
+    settings: &R,
+    registry: &TypeRegistry,
+) -> Result<ron::Value, String> {
+    let contents = ron::to_string(&TypedReflectSerializer::new(settings, registry))
+        .map_err(|e| e.to_string())?;
+    ron::from_str(&contents).map_err(|e| e.to_string())
+}
+
+/// Set the fields of `settings` found in `value`. Missing fields keep their value and unknown
+/// ones are skipped, so settings files and replays still load after fields are added or renamed.
+pub fn apply_settings<R: Reflect + Typed>(
+    settings: &mut R,
+    registry: &TypeRegistry,
+    value: ron::Value,
+) -> Result<(), String> {
+    let TypeInfo::Struct(info) = R::type_info() else {
+        unreachable!("settings are structs");
+    };
+    let ron::Value::Map(fields) = value else {
+        return Err(format!("expected a struct for {}", info.type_path()));
+    };
+    let fields = fields
+        .into_iter()
+        .filter(|(key, _)| match key {
+            ron::Value::String(name) if info.field(name).is_some() => true,
+            _ => {
+                warn!("Skipping unknown setting {key:?} in {}", info.type_path());
+                false
+            }
+        })
+        .collect();
+
+    let registration = registry
+        .get(TypeId::of::<R>())
+        .ok_or_else(|| format!("{} isn't registered", info.type_path()))?;
+    let reflected = TypedReflectDeserializer::new(registration, registry)
+        .deserialize(ron::Value::Map(fields))
+        .map_err(|e| e.to_string())?;
+    settings.apply(&*reflected);
+    Ok(())
+}
+
+fn save_settings(world: &World, path: &Path) -> Result<(), String> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let settings = SettingsFile {
+        config: TypedReflectSerializer::new(world.resource::<Config>(), &registry),
+        debug_views: TypedReflectSerializer::new(world.resource::<DebugViews>(), &registry),
+    };
+    let contents =
+        ron::ser::to_string_pretty(&settings, PrettyConfig::new()).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, contents).map_err(|e| e.to_string())
+}
+
 fn ui_config(world: &mut World) {
     let egui_context = world
         .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
@@ -113,9 +286,18 @@ fn ui_config(world: &mut World) {
         return;
     };
     let mut egui_context = egui_context.clone();
+    let player = world
+        .query_filtered::<Entity, With<Player>>()
+        .get_single(world)
+        .ok();
 
+    let (mut save, mut revert) = (false, false);
     let title = RichText::new("Config").text_style(egui::TextStyle::Body);
     egui::Window::new(title).show(egui_context.get_mut(), |ui| {
+        ui.horizontal(|ui| {
+            save = ui.button("Save").clicked();
+            revert = ui.button("Revert to defaults").clicked();
+        });
         egui::ScrollArea::both().show(ui, |ui| {
             CollapsingHeader::new("Config")
                 .default_open(true)
@@ -123,6 +305,23 @@ fn ui_config(world: &mut World) {
             CollapsingHeader::new("Debug Views")
                 .default_open(true)
                 .show(ui, |ui| ui_for_resource::<DebugViews>(world, ui));
+            if let Some(player) = player {
+                CollapsingHeader::new("Player")
+                    .default_open(false)
+                    .show(ui, |ui| ui_for_entity(world, player, ui));
+            }
         });
     });
+
+    if revert {
+        *world.resource_mut::<Config>() = Config::default();
+        *world.resource_mut::<DebugViews>() = DebugViews::default();
+    }
+    if save {
+        let path = world.resource::<SettingsPath>().0.clone();
+        match save_settings(world, &path) {
+            Ok(()) => info!("Saved settings to {:?}", path),
+            Err(e) => error!("Failed to save settings {:?}: {e}", path),
+        }
+    }
 }
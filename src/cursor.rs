@@ -20,12 +20,13 @@ fn my_cursor_system(
     q_window: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
 ) {
-    // get the camera info and transform
-    // assuming there is exactly one main camera entity, so Query::single() is OK
-    let (camera, camera_transform) = q_camera.single();
-
-    // There is only one primary window, so we can similarly get it from the query:
-    let window = q_window.single();
+    // get the camera info and transform, and the primary window
+    // neither exists when running headless
+    let (Ok((camera, camera_transform)), Ok(window)) =
+        (q_camera.get_single(), q_window.get_single())
+    else {
+        return;
+    };
 
     // check if the cursor is inside the window and get its position
     // then, ask bevy to convert into world coordinates, and truncate to discard Z
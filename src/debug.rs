@@ -3,6 +3,7 @@ use bevy::{
         basic::AQUA,
         css::{BLUE, GRAY, GREEN, LIME, MAGENTA, RED, YELLOW},
     },
+    math::vec2,
     prelude::*,
     utils::hashbrown::HashMap,
 };
@@ -13,8 +14,10 @@ use bevy_rapier2d::{
 
 use crate::{
     config::DebugViews,
-    spawner::{Spawner, SpawnerTrigger, SpawnerTriggerEvent},
-    tilemap::PlayerSpawn,
+    patrol::PatrolPath,
+    spawner::{SpawnArea, Spawner},
+    tilemap::{ObjectId, PlayerSpawn},
+    trigger::{ActionEvent, TriggerZone},
 };
 
 /// Render debug info if enabled in config
@@ -66,13 +69,14 @@ fn render_velocity(
     }
 }
 
-/// Render triggers, enemy spawners, and player spawn.
+/// Render triggers, enemy spawners, patrol paths, and player spawn.
 fn render_objects(
     debug_views: Res<DebugViews>,
     time: Res<Time>,
-    mut spawner_events: EventReader<SpawnerTriggerEvent>,
-    q_triggers: Query<(&mut SpawnerTrigger, &Collider, &GlobalTransform)>,
+    mut action_events: EventReader<ActionEvent>,
+    q_triggers: Query<(&ObjectId, &TriggerZone, &Collider, &GlobalTransform)>,
     q_spawners: Query<(&Spawner, &GlobalTransform)>,
+    q_paths: Query<&PatrolPath>,
     q_player_spawn: Query<&GlobalTransform, With<PlayerSpawn>>,
     mut timers: Local<HashMap<u32, Timer>>,
     mut gizmos: Gizmos,
@@ -117,16 +121,61 @@ fn render_objects(
             get_inactive_color(color)
         };
         gizmos.circle_2d(pos, SPAWNER_RADIUS, color);
+
+        // Outline the area enemies are placed in
+        match &spawner.area {
+            SpawnArea::Point if spawner.scatter_radius > 0.0 => {
+                gizmos.circle_2d(pos, spawner.scatter_radius, color);
+            }
+            SpawnArea::Point => (),
+            SpawnArea::Ring { inner_radius } => {
+                gizmos.circle_2d(pos, *inner_radius, color);
+                gizmos.circle_2d(pos, spawner.scatter_radius, color);
+            }
+            SpawnArea::Rect { x_axis, y_axis } => {
+                let corners = [Vec2::ZERO, *x_axis, *x_axis + *y_axis, *y_axis, Vec2::ZERO];
+                gizmos.linestrip_2d(corners.map(|corner| pos + corner), color);
+            }
+            SpawnArea::Ellipse {
+                center,
+                x_axis,
+                y_axis,
+            } => {
+                let half_size = vec2(x_axis.length(), y_axis.length());
+                gizmos.ellipse_2d(pos + *center, x_axis.to_angle(), half_size, color);
+            }
+            SpawnArea::Polygon { points } => {
+                let first = points.first().copied();
+                gizmos.linestrip_2d(points.iter().copied().chain(first).map(|p| pos + p), color);
+            }
+        }
     }
 
-    // Spawner triggers that were just triggered
-    let activated_triggers = spawner_events.read().map(|e| e.0).collect::<Vec<_>>();
+    // Draw patrol paths with the color of the spawner that uses them
+    for path in q_paths.iter() {
+        let spawner = spawner_map
+            .values()
+            .find(|(spawner, _, _)| spawner.path_id == Some(path.id));
+
+        let color = if let Some(&(_, spawner_pos, color)) = spawner {
+            gizmos.line_2d(spawner_pos, path.points[0], get_inactive_color(color));
+            *color
+        } else {
+            get_inactive_color(&GRAY)
+        };
+
+        let first = path.closed.then_some(path.points[0]);
+        gizmos.linestrip_2d(path.points.iter().copied().chain(first), color);
+    }
+
+    // Triggers that just fired
+    let fired_triggers = action_events.read().map(|e| e.source).collect::<Vec<_>>();
 
     // Draw trigger zones
-    for (trigger, collider, trigger_transform) in q_triggers.iter() {
+    for (&ObjectId(id), trigger, collider, trigger_transform) in q_triggers.iter() {
         // `timers` stores a timer per trigger which determines how long the debug render flashes
         // when the trigger is activated
-        let timer = timers.entry(trigger.id()).or_default();
+        let timer = timers.entry(id).or_default();
         timer.tick(time.delta());
 
         // Get rotation and position
@@ -137,28 +186,33 @@ fn render_objects(
         angle *= axis.z;
         let position = position.xy();
 
-        // Determine which color to render with
-        let color =
-            if let Some(&(spawner, spawner_pos, color)) = spawner_map.get(&trigger.spawner_id) {
-                // If this trigger just actived a spawner, start timer to highlight in debug view
-                if activated_triggers.contains(trigger)
-                    && !spawner.active
-                    && spawner.count != spawner.num_spawn
-                {
-                    *timer = Timer::from_seconds(0.196, TimerMode::Once);
-                }
-
-                let color = if timer.finished() {
-                    get_inactive_color(color)
-                } else {
-                    *color
-                };
-                gizmos.line_2d(position, spawner_pos, color);
-                color
+        // If this trigger just fired, start timer to highlight in debug view
+        if fired_triggers.contains(&id) {
+            *timer = Timer::from_seconds(0.196, TimerMode::Once);
+        }
+        let highlight = |color: &Srgba| {
+            if timer.finished() {
+                get_inactive_color(color)
             } else {
-                // Trigger is not connected to any spawner
-                get_inactive_color(colors.next().unwrap())
+                *color
+            }
+        };
+
+        // Use the color of the first connected spawner and draw a line to each of them
+        let mut color = None;
+        for spawner_id in trigger.spawner_ids() {
+            let Some(&(_, spawner_pos, spawner_color)) = spawner_map.get(&spawner_id) else {
+                continue;
             };
+            let spawner_color = highlight(spawner_color);
+            gizmos.line_2d(position, spawner_pos, spawner_color);
+            color.get_or_insert(spawner_color);
+        }
+        // Spent or disabled triggers and triggers not connected to any spawner are muted
+        let color = match color {
+            Some(color) if !trigger.spent && trigger.enabled => color,
+            _ => get_inactive_color(colors.next().unwrap()),
+        };
 
         // Finally draw the shape
         match collider.as_typed_shape() {
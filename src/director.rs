@@ -0,0 +1,355 @@
+use bevy::{prelude::*, time::Stopwatch};
+use serde::Deserialize;
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    chaser::Chaser,
+    physics::SimulationSet,
+    player::{ResetEvent, TimeSinceCollision},
+    spawner::{update_spawners, EnemyKind, Spawner, SpawnerAction, SpawnerTriggerEvent},
+};
+
+/// Wave scripts are loaded from here
+const WAVE_DIR: &str = "assets/waves";
+const WAVE_UI_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+
+/// Schedules waves on spawners from a per-level script
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_wave_text)
+            .add_systems(
+                FixedUpdate,
+                (
+                    prepare_spawners,
+                    reset_director,
+                    update_intensity,
+                    start_triggered_waves,
+                    run_waves,
+                )
+                    .chain()
+                    .before(update_spawners)
+                    .in_set(SimulationSet)
+                    .run_if(resource_exists::<WaveDirector>),
+            )
+            .add_systems(
+                Update,
+                update_wave_ui.run_if(resource_exists::<WaveDirector>),
+            );
+    }
+}
+
+/// Wave schedule loaded from a RON file in `assets/waves/`. Levels pick a script with the
+/// `waves` map property.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveScript {
+    /// Seconds between waves
+    pub break_time: f32,
+    /// Intensity added per minute of play
+    pub ramp_per_minute: f32,
+    /// Intensity added when the player hasn't been hit for `performance_window` seconds
+    pub performance_weight: f32,
+    pub performance_window: f32,
+    pub max_intensity: f32,
+    /// Start over from the first wave after the last one. Intensity keeps growing.
+    pub repeat: bool,
+    pub waves: Vec<Wave>,
+}
+
+impl WaveScript {
+    pub fn load(filename: &str) -> Self {
+        let path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join(WAVE_DIR)
+            .join(filename);
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read wave script {:?}: {e}", path));
+        let script: WaveScript = ron::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse wave script {:?}: {e}", path));
+        assert!(
+            !script.waves.is_empty(),
+            "Wave script {:?} has no waves",
+            path
+        );
+        script
+    }
+
+    fn uses_spawner(&self, id: u32) -> bool {
+        self.waves.iter().any(|wave| wave.uses_spawner(id))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Wave {
+    pub groups: Vec<WaveGroup>,
+    /// The wave ends once this many seconds have passed...
+    pub duration: f32,
+    /// ...or fewer than this many chasers are left
+    pub clear_below: usize,
+}
+
+impl Wave {
+    fn uses_spawner(&self, id: u32) -> bool {
+        self.groups.iter().any(|group| group.spawners.contains(&id))
+    }
+}
+
+/// Enemies of one kind split between `spawners`. Counts and pacing are scaled by intensity.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveGroup {
+    pub kind: EnemyKind,
+    pub count: u32,
+    /// Object ids of the spawners to use
+    pub spawners: Vec<u32>,
+    /// Seconds after the wave starts
+    #[serde(default)]
+    pub delay: f32,
+    /// Seconds between spawns
+    pub interval: f32,
+}
+
+#[derive(Debug)]
+enum WaveState {
+    Break(Timer),
+    Running {
+        time: Stopwatch,
+        /// Groups of the current wave that were handed to spawners
+        started: Vec<bool>,
+    },
+    Finished,
+}
+
+#[derive(Resource, Debug)]
+pub struct WaveDirector {
+    script: WaveScript,
+    /// Index of the current or next wave
+    wave: usize,
+    /// Waves started since the level began
+    pub wave_count: u32,
+    state: WaveState,
+    elapsed: Stopwatch,
+    /// Multiplies counts and divides intervals of new groups
+    pub intensity: f32,
+}
+
+impl WaveDirector {
+    pub fn new(script: WaveScript) -> Self {
+        let state = WaveState::Break(Timer::from_seconds(script.break_time, TimerMode::Once));
+        WaveDirector {
+            script,
+            wave: 0,
+            wave_count: 0,
+            state,
+            elapsed: Stopwatch::new(),
+            intensity: 1.0,
+        }
+    }
+
+    fn start_wave(&mut self) {
+        self.wave_count += 1;
+        info!("Wave {} (intensity {:.2})", self.wave_count, self.intensity);
+        self.state = WaveState::Running {
+            time: Stopwatch::new(),
+            started: vec![false; self.script.waves[self.wave].groups.len()],
+        };
+    }
+
+    fn end_wave(&mut self) {
+        self.wave += 1;
+        if self.wave < self.script.waves.len() || self.script.repeat {
+            self.wave %= self.script.waves.len();
+            let timer = Timer::from_seconds(self.script.break_time, TimerMode::Once);
+            self.state = WaveState::Break(timer);
+        } else {
+            self.state = WaveState::Finished;
+        }
+    }
+}
+
+/// Hand spawners used by the script over to the director
+fn prepare_spawners(
+    director: Res<WaveDirector>,
+    mut q_spawners: Query<&mut Spawner, Added<Spawner>>,
+) {
+    for mut spawner in q_spawners.iter_mut() {
+        if director.script.uses_spawner(spawner.id) {
+            spawner.directed = true;
+            spawner.active = false;
+            spawner.active_default = false;
+        }
+    }
+}
+
+/// Start the script over when the level is reset
+fn reset_director(mut director: ResMut<WaveDirector>, mut reset_events: EventReader<ResetEvent>) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    let script = director.script.clone();
+    *director = WaveDirector::new(script);
+}
+
+/// Intensity grows with time played and with how long the player has avoided getting hit
+fn update_intensity(
+    time: Res<Time>,
+    mut director: ResMut<WaveDirector>,
+    q_stopwatch: Query<&TimeSinceCollision>,
+) {
+    let director = &mut *director;
+    director.elapsed.tick(time.delta());
+
+    let script = &director.script;
+    let minutes = director.elapsed.elapsed_secs() / 60.0;
+    let performance = q_stopwatch.get_single().map_or(0.0, |stopwatch| {
+        (stopwatch.elapsed_secs() / script.performance_window).min(1.0)
+    });
+    let intensity =
+        1.0 + minutes * script.ramp_per_minute + performance * script.performance_weight;
+    director.intensity = intensity.min(script.max_intensity);
+}
+
+/// Triggers for a spawner used by the next wave cut the break short
+fn start_triggered_waves(
+    mut director: ResMut<WaveDirector>,
+    mut spawner_events: EventReader<SpawnerTriggerEvent>,
+) {
+    for event in spawner_events.read() {
+        if !matches!(director.state, WaveState::Break(_)) {
+            continue;
+        }
+        if !matches!(
+            event.action,
+            SpawnerAction::Activate | SpawnerAction::Toggle
+        ) {
+            continue;
+        }
+        let wave = &director.script.waves[director.wave];
+        if event.spawner_ids.iter().any(|&id| wave.uses_spawner(id)) {
+            director.start_wave();
+        }
+    }
+}
+
+fn run_waves(
+    time: Res<Time>,
+    mut director: ResMut<WaveDirector>,
+    mut q_spawners: Query<&mut Spawner>,
+    q_chasers: Query<(), With<Chaser>>,
+) {
+    let director = &mut *director;
+    let wave = &director.script.waves[director.wave];
+
+    match &mut director.state {
+        WaveState::Break(timer) => {
+            timer.tick(time.delta());
+            if timer.finished() {
+                director.start_wave();
+            }
+        }
+        WaveState::Running {
+            time: wave_time,
+            started,
+        } => {
+            wave_time.tick(time.delta());
+
+            for (group, started) in wave.groups.iter().zip(started.iter_mut()) {
+                if *started || wave_time.elapsed_secs() < group.delay {
+                    continue;
+                }
+                *started = start_group(group, director.intensity, &mut q_spawners);
+            }
+
+            let all_started = started.iter().all(|&s| s);
+            let spawning = q_spawners
+                .iter()
+                .any(|s| s.directed && s.active && wave.uses_spawner(s.id));
+            let cleared = q_chasers.iter().count() < wave.clear_below;
+            let timed_out = wave_time.elapsed_secs() >= wave.duration;
+
+            if all_started && !spawning && (cleared || timed_out) {
+                director.end_wave();
+            }
+        }
+        WaveState::Finished => (),
+    }
+}
+
+/// Split the scaled group count between its spawners and start them. Waits while any of them
+/// is still spawning an earlier group, so groups sharing a spawner queue up instead of cutting
+/// each other short. Returns whether the group started.
+fn start_group(group: &WaveGroup, intensity: f32, q_spawners: &mut Query<&mut Spawner>) -> bool {
+    if group.spawners.is_empty() {
+        return true;
+    }
+    let busy = q_spawners
+        .iter()
+        .any(|spawner| spawner.active && group.spawners.contains(&spawner.id));
+    if busy {
+        return false;
+    }
+    let total = (group.count as f32 * intensity).ceil() as u32;
+    let per_spawner = total / group.spawners.len() as u32;
+    let remainder = total % group.spawners.len() as u32;
+    let interval = group.interval / intensity;
+
+    for mut spawner in q_spawners.iter_mut() {
+        let Some(index) = group.spawners.iter().position(|&id| id == spawner.id) else {
+            continue;
+        };
+        let num_spawn = per_spawner + u32::from((index as u32) < remainder);
+        if num_spawn == 0 {
+            continue;
+        }
+        spawner.kind = group.kind;
+        spawner.num_spawn = num_spawn as i32;
+        spawner.interval = interval;
+        spawner.delay = interval;
+        spawner.repeats = false;
+        spawner.count = 0;
+        spawner.timer = Timer::new(Duration::ZERO, TimerMode::Repeating);
+        spawner.active = true;
+    }
+    true
+}
+
+/// Draws wave number on screen
+#[derive(Component)]
+struct WaveUi;
+
+fn spawn_wave_text(mut commands: Commands) {
+    commands.spawn((
+        WaveUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: WAVE_UI_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(75.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_wave_ui(director: Res<WaveDirector>, mut text_query: Query<&mut Text, With<WaveUi>>) {
+    let value = match director.state {
+        WaveState::Break(ref timer) => format!(
+            "wave {} in {:.0}",
+            director.wave_count + 1,
+            timer.remaining_secs().ceil()
+        ),
+        WaveState::Running { .. } => format!("wave {}", director.wave_count),
+        WaveState::Finished => "all waves cleared".to_string(),
+    };
+
+    // Avoid updating text layout every frame
+    let mut text = text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
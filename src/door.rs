@@ -0,0 +1,114 @@
+use bevy::{
+    math::vec2,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+use tiled::ObjectData;
+
+use crate::{physics::WALL_GROUP, player::ResetEvent, properties::object_properties};
+
+const DOOR_COLOR: Color = Color::srgb(0.55, 0.4, 0.25);
+const DOOR_Z: f32 = 3.0;
+
+/// Doors placed in Tiled that level actions open and close
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (prepare_doors, reset_doors, update_doors).chain())
+            .register_type::<Door>();
+    }
+}
+
+/// Blocks like a wall while closed. Doors don't change the flowfield so chasers wait behind
+/// closed doors.
+#[derive(Component, Debug, Reflect)]
+pub struct Door {
+    pub open: bool,
+    open_default: bool,
+    size: Vec2,
+}
+
+object_properties! {
+    /// Custom properties of a [`Door`] object
+    struct DoorProperties {
+        open: bool = false,
+    }
+}
+
+impl Door {
+    pub fn from_object(object_data: &ObjectData) -> Self {
+        let tiled::ObjectShape::Rect { width, height } = object_data.shape else {
+            panic!("Door must be a rectangle. Object ID: {}", object_data.id());
+        };
+        let properties = DoorProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        let open = properties.open;
+        Door {
+            open,
+            open_default: open,
+            size: vec2(width, height),
+        }
+    }
+
+    pub fn apply(&mut self, action: DoorAction) {
+        self.open = match action {
+            DoorAction::Open => true,
+            DoorAction::Close => false,
+            DoorAction::Toggle => !self.open,
+        };
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorAction {
+    Open,
+    Close,
+    Toggle,
+}
+
+/// Add visuals to doors loaded from the tilemap. The collider is added by the tilemap.
+fn prepare_doors(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_doors: Query<(Entity, &Door, &Transform), Added<Door>>,
+) {
+    for (entity, door, transform) in q_doors.iter() {
+        commands.entity(entity).insert((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Rectangle::from_size(door.size))),
+                material: materials.add(ColorMaterial::from_color(DOOR_COLOR)),
+                transform: transform.with_translation(transform.translation.with_z(DOOR_Z)),
+                ..default()
+            },
+            CollisionGroups::new(WALL_GROUP, Group::ALL),
+            Name::new("Door"),
+        ));
+    }
+}
+
+fn reset_doors(mut reset_events: EventReader<ResetEvent>, mut q_doors: Query<&mut Door>) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for mut door in q_doors.iter_mut() {
+        door.open = door.open_default;
+    }
+}
+
+/// Open doors have no collider and are hidden
+fn update_doors(mut commands: Commands, q_doors: Query<(Entity, &Door), Changed<Door>>) {
+    for (entity, door) in q_doors.iter() {
+        if door.open {
+            commands
+                .entity(entity)
+                .insert((ColliderDisabled, Visibility::Hidden));
+        } else {
+            commands
+                .entity(entity)
+                .remove::<ColliderDisabled>()
+                .insert(Visibility::Visible);
+        }
+    }
+}
@@ -15,7 +15,10 @@ use crate::{
     chaser::Chaser,
     config::{Config, DebugViews},
     cursor::MyWorldCoords,
+    patrol::Patroller,
+    physics::SimulationSet,
     player::{self, Player},
+    shooter::Shooter,
     tilemap::*,
 };
 
@@ -26,17 +29,17 @@ impl Plugin for FlowfieldPlugin {
     fn build(&self, app: &mut App) {
         app.world_mut().spawn(Flowfield::default());
         app.add_systems(
-            Update,
+            FixedUpdate,
             (
                 update_target,
                 update_cost,
                 // update_flowfield,
                 apply_force,
-                draw_flowfield,
             )
-                .chain(),
+                .chain()
+                .in_set(SimulationSet),
         )
-        .add_systems(Update, print_cost_at_cursor)
+        .add_systems(Update, (draw_flowfield, print_cost_at_cursor))
         .add_systems(
             PostStartup,
             setup_flowfield
@@ -253,6 +256,11 @@ impl Flowfield {
         }
         Some(self.get_index_at_tile_unchecked(tile))
     }
+    /// Cost from the most recent (possibly still in progress) pass over the cost grid
+    pub fn get_cost_at_tile(&self, tile: Vec2) -> Option<u32> {
+        let index = self.get_index_at_tile(tile)?;
+        self.cost_grid.get(index).map(|&(cost, _)| cost)
+    }
     pub fn get_index_at_tile_unchecked(&self, tile: Vec2) -> usize {
         (tile.x as isize + tile.y as isize * self.width) as usize
     }
@@ -293,7 +301,7 @@ fn setup_flowfield(q_tilemap: Query<&Tilemap>, mut q_flowfield: Query<&mut Flowf
     info!("setup flowfield!");
 }
 
-fn update_target(
+pub fn update_target(
     q_player_transform: Query<&Transform, With<Player>>,
     mut q_flowfield: Query<&mut Flowfield>,
     q_map: Query<&Tilemap>,
@@ -338,7 +346,7 @@ impl Ord for Node {
 }
 
 // TODO cleanup
-fn update_cost(
+pub fn update_cost(
     time: Res<Time>,
     config: Res<Config>,
     mut q_flowfield: Query<&mut Flowfield>,
@@ -351,9 +359,11 @@ fn update_cost(
     // let target_changed = !q_target.is_empty();
     let storage = q_tile_storage.single();
 
+    // Runs on the fixed timestep, so the budget is the same at any framerate
     let tile_count = flowfield.width * flowfield.height;
-    let fps = 1.0 / time.delta_seconds();
-    let iter_per_update = (tile_count as f32 / (fps * config.seconds_per_iter)) as usize;
+    let steps_per_second = 1.0 / time.delta_seconds();
+    let iter_per_update =
+        (tile_count as f32 / (steps_per_second * config.seconds_per_iter)) as usize;
     // info!(iter_per_update);
 
     if !flowfield.target_changed && flowfield.heap.is_empty() {
@@ -646,7 +656,10 @@ fn update_flowfield(
 pub fn apply_force(
     mut q_flowfield: Query<&mut Flowfield>,
     mut q_map: Query<&Tilemap>,
-    mut q_chasers: Query<(&mut ExternalForce, &mut Transform), With<Chaser>>,
+    mut q_chasers: Query<
+        (&mut ExternalForce, &mut Transform),
+        (With<Chaser>, Without<Patroller>, Without<Shooter>),
+    >,
     q_tile_storage: Query<&TileStorage>,
     time: Res<Time>,
     config: Res<Config>,
@@ -1,4 +1,7 @@
-use bevy::{input::gamepad::{GamepadConnection, GamepadEvent}, prelude::*};
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadEvent},
+    prelude::*,
+};
 
 /// Handles connection to gamepad
 pub struct GamepadPlugin;
@@ -0,0 +1,233 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::plugin::PhysicsSet;
+use bevy_svg::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    leaderboard::{rank_runs, save_dir},
+    player::{Player, PLAYER_COLOR, PLAYER_RADIUS},
+    rng::GameRng,
+    score::{RunOverEvent, Score},
+    tilemap::{load_tilemap, Args},
+};
+
+/// Directory in the save directory the best run of each level is saved to
+const GHOST_DIR: &str = "ghosts";
+/// Seconds between recorded positions. Positions in between are interpolated.
+const GHOST_SAMPLE_TIME: f32 = 1.0 / 30.0;
+const GHOST_ALPHA: f32 = 0.35;
+/// Drawn just below the player
+const GHOST_Z: f32 = 4.0;
+
+/// Saves the best run of each level and plays it back as a ghost next to the player.
+/// The best run is the one at the top of the leaderboard.
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_ghost.after(load_tilemap))
+            .add_systems(
+                FixedPostUpdate,
+                record_run
+                    .after(PhysicsSet::Writeback)
+                    .run_if(resource_exists::<Ghosts>),
+            )
+            .add_systems(
+                Update,
+                (spawn_ghost, move_ghost)
+                    .chain()
+                    .run_if(resource_exists::<Ghosts>),
+            )
+            // `RunOverEvent` is sent in PostUpdate
+            .add_systems(Last, end_run.run_if(resource_exists::<Ghosts>));
+    }
+}
+
+/// Player positions over one run of a level
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GhostRun {
+    pub seed: u64,
+    /// Ghosts saved before scores were kept have none, so any scored run beats them
+    #[serde(default)]
+    pub score: u32,
+    /// Seconds survived
+    pub secs: f32,
+    /// Player position every [`GHOST_SAMPLE_TIME`] seconds
+    pub positions: Vec<Vec2>,
+}
+
+impl GhostRun {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Position `secs` into the run. `None` once the run has ended.
+    pub fn position_at(&self, secs: f32) -> Option<Vec2> {
+        let t = secs / GHOST_SAMPLE_TIME;
+        let i = t as usize;
+        let (a, b) = (self.positions.get(i)?, self.positions.get(i + 1)?);
+        Some(a.lerp(*b, t.fract()))
+    }
+}
+
+#[derive(Resource, Debug)]
+struct Ghosts {
+    /// Where the level's best run is saved. `None` without a data directory.
+    path: Option<PathBuf>,
+    best: Option<GhostRun>,
+    current: GhostRun,
+    /// Replays and benchmarks aren't real runs, so they are played against the ghost but not
+    /// saved
+    save: bool,
+}
+
+impl Ghosts {
+    /// Keep the current run if it ranks above the best one and start a new one
+    fn end_run(&mut self, score: &Score, seed: u64) {
+        let run = std::mem::take(&mut self.current);
+        let is_best = self.best.as_ref().map_or(true, |best| {
+            rank_runs((score.total(), run.secs), (best.score, best.secs)).is_lt()
+        });
+        if !self.save || !is_best || run.positions.is_empty() {
+            return;
+        }
+        info!("New best run: {} in {:.2}s", score.total(), run.secs);
+        let run = GhostRun {
+            seed,
+            score: score.total(),
+            ..run
+        };
+        if let Some(path) = self.path.as_ref() {
+            if let Err(e) = run.save(path) {
+                error!("Failed to save ghost {:?}: {e}", path);
+            }
+        }
+        self.best = Some(run);
+    }
+}
+
+/// Marker for the entity showing the best run
+#[derive(Component)]
+struct Ghost;
+
+fn load_ghost(mut commands: Commands, args: Res<Args>) {
+    let Some(map) = args.map.as_deref() else {
+        return;
+    };
+    let path = save_dir().map(|dir| dir.join(GHOST_DIR).join(map).with_extension("ron"));
+    let best = path.as_ref().filter(|path| path.exists()).and_then(|path| {
+        GhostRun::load(path)
+            .inspect_err(|e| error!("Failed to load ghost {:?}: {e}", path))
+            .ok()
+    });
+    commands.insert_resource(Ghosts {
+        path,
+        best,
+        current: GhostRun::default(),
+        save: args.replay.is_none() && args.bench.is_none(),
+    });
+}
+
+fn record_run(
+    time: Res<Time>,
+    mut ghosts: ResMut<Ghosts>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    let Ok(transform) = q_player.get_single() else {
+        return;
+    };
+    let run = &mut ghosts.current;
+    run.secs += time.delta_seconds();
+    while run.positions.len() as f32 * GHOST_SAMPLE_TIME <= run.secs {
+        run.positions.push(transform.translation.xy());
+    }
+}
+
+/// Spawn a translucent player without a collider once there is a player and a best run
+fn spawn_ghost(
+    mut commands: Commands,
+    ghosts: Res<Ghosts>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_player: Query<(), With<Player>>,
+    q_ghost: Query<(), With<Ghost>>,
+) {
+    if q_player.is_empty() || !q_ghost.is_empty() || ghosts.best.is_none() {
+        return;
+    }
+    commands
+        .spawn((
+            Ghost,
+            Name::new("Ghost"),
+            Svg2dBundle {
+                // bevy_svg can't tint, so this is player.svg with its opacity lowered
+                svg: asset_server.load("svgs/ghost.svg"),
+                origin: Origin::TopLeft,
+                transform: Transform::from_xyz(0.0, 0.0, GHOST_Z),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(PLAYER_RADIUS - 1.0))),
+                material: materials.add(ColorMaterial::from_color(
+                    PLAYER_COLOR.with_alpha(GHOST_ALPHA),
+                )),
+                transform: Transform::from_xyz(0.0, 0.0, -1.0),
+                ..default()
+            });
+        });
+}
+
+/// Put the ghost where the best run was at this point. Hidden once the best run has ended.
+fn move_ghost(
+    ghosts: Res<Ghosts>,
+    fixed_time: Res<Time<Fixed>>,
+    mut q_ghost: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+) {
+    let (Ok((mut transform, mut visibility)), Some(best)) =
+        (q_ghost.get_single_mut(), ghosts.best.as_ref())
+    else {
+        return;
+    };
+    // Between simulation steps like the interpolated player
+    let secs = ghosts.current.secs + fixed_time.overstep().as_secs_f32();
+    match best.position_at(secs) {
+        Some(pos) => {
+            transform.translation = pos.extend(GHOST_Z);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// Runs end when the level resets. Quitting mid-run still counts.
+fn end_run(
+    rng: Res<GameRng>,
+    score: Res<Score>,
+    mut ghosts: ResMut<Ghosts>,
+    mut run_over_events: EventReader<RunOverEvent>,
+    mut exit_events: EventReader<AppExit>,
+) {
+    if let Some(event) = run_over_events.read().last() {
+        ghosts.end_run(&event.score, rng.seed());
+    }
+    if exit_events.read().last().is_some() {
+        ghosts.end_run(&score, rng.seed());
+    }
+}
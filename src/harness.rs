@@ -0,0 +1,254 @@
+//! Boots a level headlessly so tests can drive it with scripted input and check the world
+
+use bevy::prelude::*;
+use std::path::Path;
+
+use crate::{
+    chaser::Chaser,
+    config::Config,
+    flowfield::WALL_BITMASK,
+    headless::HeadlessPlugin,
+    player::{read_player_action, Player, PlayerAction, PlayerInputSet},
+    replay::{recorded_config, Playback, Replay, ReplayFrame},
+    spawner::Spawner,
+    tilemap::{Args, TileStorage, Tilemap},
+    trigger::ActionEvent,
+    GameplayPlugins,
+};
+
+/// Seed used by every test level, so failures can be reproduced
+pub const TEST_SEED: u64 = 1;
+/// A chaser this close to the player has reached it
+const REACH_DIST: f32 = 24.0;
+
+/// A level running in a headless [`App`]. Every frame advances the game by 1/60 s.
+pub struct TestLevel {
+    app: App,
+}
+
+impl TestLevel {
+    /// Load a map from `assets/levels/` and run its first frame
+    pub fn load(map: &str) -> Self {
+        Self::start(Args {
+            map: Some(map.to_string()),
+            seed: Some(TEST_SEED),
+            headless: true,
+            ..default()
+        })
+    }
+
+    /// Load a replay and run its first frame. Its input replaces the scripted input.
+    pub fn load_replay(path: &Path) -> Self {
+        Self::start(Args {
+            replay: Some(path.to_path_buf()),
+            headless: true,
+            ..default()
+        })
+    }
+
+    fn start(args: Args) -> Self {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin { ticks: None })
+            .insert_resource(args)
+            .add_plugins(GameplayPlugins)
+            .init_resource::<ScriptedAction>()
+            .init_resource::<FiredTriggers>()
+            .init_resource::<RecordedFrames>()
+            .add_systems(
+                PreUpdate,
+                apply_scripted_action
+                    .run_if(not(resource_exists::<Playback>))
+                    .in_set(PlayerInputSet)
+                    .after(read_player_action),
+            )
+            .add_systems(Last, (record_fired_triggers, record_frame));
+        app.finish();
+        app.cleanup();
+
+        let mut level = TestLevel { app };
+        level.run(1);
+        level
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Seconds of game time since the level was loaded
+    pub fn elapsed_secs(&self) -> f32 {
+        self.app
+            .world()
+            .resource::<Time<Virtual>>()
+            .elapsed_seconds()
+    }
+
+    /// Config and input of every frame so far, like a recording made with `--record`
+    pub fn replay(&self) -> Replay {
+        let world = self.app.world();
+        let args = world.resource::<Args>();
+        Replay {
+            map: args.map.clone().unwrap_or_default(),
+            seed: args.seed.unwrap_or(TEST_SEED),
+            config: recorded_config(
+                world.resource::<Config>(),
+                world.resource::<AppTypeRegistry>(),
+            ),
+            frames: world.resource::<RecordedFrames>().0.clone(),
+        }
+    }
+
+    /// Run `frames` frames with the current action
+    pub fn run(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.app.update();
+        }
+    }
+
+    /// Hold `action` for `frames` frames. One-shot actions like `dash` should only be held for
+    /// a single frame.
+    pub fn run_with(&mut self, action: PlayerAction, frames: u32) {
+        self.world().resource_mut::<ScriptedAction>().0 = action;
+        self.run(frames);
+        self.world().resource_mut::<ScriptedAction>().0 = PlayerAction::default();
+    }
+
+    /// Run each `(frames, action)` step in order
+    pub fn run_script(&mut self, steps: &[(u32, PlayerAction)]) {
+        for &(frames, action) in steps {
+            self.run_with(action, frames);
+        }
+    }
+
+    /// Run until `done` returns true or `secs` seconds pass. Returns whether `done` returned
+    /// true.
+    pub fn run_until(&mut self, secs: f32, mut done: impl FnMut(&mut World) -> bool) -> bool {
+        let end = self.elapsed_secs() + secs;
+        while self.elapsed_secs() < end {
+            if done(self.world()) {
+                return true;
+            }
+            self.app.update();
+        }
+        done(self.world())
+    }
+
+    pub fn player_pos(&mut self) -> Vec2 {
+        self.world()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(self.world())
+            .translation
+            .xy()
+    }
+
+    pub fn chaser_positions(&mut self) -> Vec<Vec2> {
+        self.world()
+            .query_filtered::<&Transform, With<Chaser>>()
+            .iter(self.world())
+            .map(|t| t.translation.xy())
+            .collect()
+    }
+
+    /// Spawner with Tiled object id `id`
+    pub fn spawner(&mut self, id: u32) -> &Spawner {
+        let world = self.app.world_mut();
+        world
+            .query::<&Spawner>()
+            .iter(world)
+            .find(|spawner| spawner.id == id)
+            .unwrap_or_else(|| panic!("No spawner with id {id}"))
+    }
+
+    /// Run until a chaser is next to the player. Fails if none gets there within `secs`.
+    pub fn assert_chasers_reach_player(&mut self, secs: f32) {
+        let reached = self.run_until(secs, |world| {
+            let Ok(player) = world
+                .query_filtered::<&Transform, With<Player>>()
+                .get_single(world)
+                .map(|t| t.translation.xy())
+            else {
+                return false;
+            };
+            world
+                .query_filtered::<&Transform, With<Chaser>>()
+                .iter(world)
+                .any(|t| t.translation.xy().distance(player) < REACH_DIST)
+        });
+        assert!(
+            reached,
+            "No chaser reached the player within {secs}s. Player at {}, chasers at {:?}",
+            self.player_pos(),
+            self.chaser_positions()
+        );
+    }
+
+    /// Fails if a chaser's center is on a solid wall tile
+    pub fn assert_no_chaser_in_wall(&mut self) {
+        let world = self.app.world_mut();
+        let mut q_chasers = world.query_filtered::<&Transform, With<Chaser>>();
+        let (map, storage) = world.query::<(&Tilemap, &TileStorage)>().single(world);
+        let in_wall = q_chasers
+            .iter(world)
+            .map(|t| t.translation.xy())
+            .filter(|pos| {
+                let tile = map.world_to_tile_coords(pos);
+                let index = tile.x as usize + tile.y as usize * map.width;
+                storage.0.get(index) == Some(&WALL_BITMASK)
+            })
+            .collect::<Vec<_>>();
+        assert!(in_wall.is_empty(), "Chasers inside walls at {in_wall:?}");
+    }
+
+    /// Fails unless spawner `id` has placed exactly `count` enemies
+    pub fn assert_spawner_emitted(&mut self, id: u32, count: i32) {
+        let spawned = self.spawner(id).spawned;
+        assert_eq!(
+            spawned, count,
+            "Spawner {id} emitted {spawned} enemies, expected {count}"
+        );
+    }
+
+    /// Fails unless the trigger or timer with object id `id` has fired
+    pub fn assert_trigger_fired(&mut self, id: u32) {
+        let fired = &self.app.world().resource::<FiredTriggers>().0;
+        assert!(
+            fired.contains(&id),
+            "Trigger {id} didn't fire. Fired: {fired:?}"
+        );
+    }
+}
+
+/// Action for the current frame. Replaces live input.
+#[derive(Resource, Default)]
+struct ScriptedAction(PlayerAction);
+
+/// Object ids of every trigger and timer that fired, in order
+#[derive(Resource, Default)]
+struct FiredTriggers(Vec<u32>);
+
+/// Input and frame time of every frame, in order
+#[derive(Resource, Default)]
+struct RecordedFrames(Vec<ReplayFrame>);
+
+fn apply_scripted_action(scripted: Res<ScriptedAction>, mut action: ResMut<PlayerAction>) {
+    *action = scripted.0;
+}
+
+fn record_fired_triggers(
+    mut fired: ResMut<FiredTriggers>,
+    mut action_events: EventReader<ActionEvent>,
+) {
+    fired
+        .0
+        .extend(action_events.read().map(|event| event.source));
+}
+
+fn record_frame(
+    time: Res<Time<Real>>,
+    action: Res<PlayerAction>,
+    mut recorded: ResMut<RecordedFrames>,
+) {
+    recorded.0.push(ReplayFrame {
+        delta: time.delta(),
+        action: *action,
+    });
+}
@@ -0,0 +1,69 @@
+use bevy::{
+    audio::AudioSource, core::FrameCount, gizmos::GizmoPlugin, input::InputPlugin, log::LogPlugin,
+    prelude::*, render::render_resource::Shader, time::TimeUpdateStrategy,
+};
+use bevy_fast_tilemap::prelude::Map;
+use bevy_svg::prelude::Svg;
+use std::time::Duration;
+
+/// Seconds each frame advances the game by when headless
+const HEADLESS_FRAME_TIME: f64 = 1.0 / 60.0;
+
+/// Runs the gameplay plugins with `--headless`: no window, renderer, egui or SVG rendering.
+/// Frames advance by a fixed time, so a run is as fast as the machine allows and plays out the
+/// same as with a window at 60 FPS.
+pub struct HeadlessPlugin {
+    /// Exit after this many frames. Runs until something else exits if `None`.
+    pub ticks: Option<u32>,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            LogPlugin {
+                // Asset loaders aren't added, so loading sprites, SVGs and sounds fails
+                filter: format!("{},bevy_asset=off", LogPlugin::default().filter),
+                ..default()
+            },
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+        ))
+        // Gameplay code still creates meshes and materials and draws gizmos. These only need the
+        // asset types, not anything to render them.
+        .init_asset::<Image>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Svg>()
+        .init_asset::<Map>()
+        .init_asset::<AudioSource>()
+        .init_asset::<Shader>()
+        .add_plugins(GizmoPlugin)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            HEADLESS_FRAME_TIME,
+        )));
+
+        if let Some(ticks) = self.ticks {
+            app.insert_resource(TickLimit(ticks))
+                .add_systems(Last, exit_after_ticks);
+        }
+    }
+}
+
+/// Number of frames to run for
+#[derive(Resource, Debug)]
+struct TickLimit(u32);
+
+fn exit_after_ticks(
+    limit: Res<TickLimit>,
+    frame_count: Res<FrameCount>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // Frame count is only incremented at the end of the frame
+    if frame_count.0 + 1 >= limit.0 {
+        info!("Simulated {} frames", limit.0);
+        exit.send(AppExit::Success);
+    }
+}
@@ -0,0 +1,373 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::Config,
+    player::PlayerAction,
+    replay::{recorded_config, Replay, ReplayFrame},
+    rng::GameRng,
+    score::{RunOverEvent, Score},
+    tilemap::{load_tilemap, Args},
+};
+
+/// Bump when [`SaveFile`] changes in a way old files can't be read as, and migrate the old
+/// version in [`SaveFile::load`]
+const SAVE_VERSION: u32 = 1;
+/// Directory inside the platform data directory
+const SAVE_DIR: &str = "train_game";
+const SAVE_FILE: &str = "leaderboard.ron";
+/// Replays of the records, next to the save file
+const REPLAY_DIR: &str = "replays";
+/// Records kept per level
+const TOP_N: usize = 10;
+/// Runs shorter than this are resets, not attempts
+const MIN_RECORD_SECS: f32 = 1.0;
+const LEADERBOARD_UI_COLOR: Color = Color::srgb(1.0, 0.9, 0.4);
+
+/// Keeps the best runs of each level in a save file in the platform data directory, with the
+/// replay needed to watch them again. Toggled on screen with L.
+///
+/// Replays play from a fresh start of the game, so only the first run after launch gets one.
+/// Later runs start from a level that was already played in: physics, entities and fixed
+/// timestep state differ from a fresh start, so their input wouldn't play out the same.
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Startup,
+            (load_leaderboard.after(load_tilemap), spawn_leaderboard_ui),
+        )
+        .add_systems(
+            Update,
+            (toggle_leaderboard, update_leaderboard_ui)
+                .chain()
+                .run_if(resource_exists::<Leaderboard>),
+        )
+        // `RunOverEvent` is sent in PostUpdate
+        .add_systems(
+            Last,
+            (record_frame, end_run)
+                .chain()
+                .run_if(resource_exists::<Leaderboard>),
+        );
+    }
+}
+
+/// One finished run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Record {
+    pub score: u32,
+    /// Seconds survived
+    pub secs: f32,
+    pub near_misses: u32,
+    pub seed: u64,
+    /// Replay filename in the replays directory. Only the first run after launch has one.
+    #[serde(default)]
+    pub replay: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl Record {
+    /// Ties in [`rank_runs`] go to whoever set the record first
+    fn rank(&self, other: &Self) -> Ordering {
+        rank_runs((self.score, self.secs), (other.score, other.secs))
+            .then(self.timestamp.cmp(&other.timestamp))
+    }
+}
+
+/// Order of runs given as `(score, secs)`, best first. Higher score first, ties go to the
+/// longer run.
+pub fn rank_runs(a: (u32, f32), b: (u32, f32)) -> Ordering {
+    b.0.cmp(&a.0).then(b.1.total_cmp(&a.1))
+}
+
+/// Directory in the platform data directory where records, replays and ghosts are saved
+pub fn save_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(SAVE_DIR))
+}
+
+/// Just the version, to know how to read the rest
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Records of every level. New fields need `#[serde(default)]` so older files still load.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveFile {
+    pub version: u32,
+    /// Top records of each map, best first
+    pub levels: BTreeMap<String, Vec<Record>>,
+}
+
+impl Default for SaveFile {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            levels: BTreeMap::new(),
+        }
+    }
+}
+
+impl SaveFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let header: SaveHeader = ron::from_str(&contents).map_err(|e| e.to_string())?;
+        match header.version {
+            SAVE_VERSION => ron::from_str(&contents).map_err(|e| e.to_string()),
+            version if version > SAVE_VERSION => Err(format!(
+                "saved by a newer version of the game (version {version})"
+            )),
+            // Migrations from older versions go here
+            version => Err(format!("unknown version {version}")),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::new().depth_limit(3))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Add a record to the map's table, keeping the best [`TOP_N`]. Returns its rank, starting
+    /// at 0, if it made the table, and the records that fell off.
+    fn insert(&mut self, map: &str, record: Record) -> (Option<usize>, Vec<Record>) {
+        let table = self.levels.entry(map.to_string()).or_default();
+        let index = table.partition_point(|r| r.rank(&record).is_le());
+        table.insert(index, record);
+        let dropped = table.split_off(table.len().min(TOP_N));
+        ((index < TOP_N).then_some(index), dropped)
+    }
+}
+
+#[derive(Resource, Debug)]
+struct Leaderboard {
+    /// Directory with the save file and the replays
+    dir: PathBuf,
+    map: String,
+    records: SaveFile,
+    /// Input since launch, saved as the replay of the first run's record. `None` once the first
+    /// run is over.
+    frames: Option<Vec<ReplayFrame>>,
+    /// Config at launch, saved with the replay
+    config: Option<ron::Value>,
+    /// False if the save file couldn't be read, so it isn't overwritten, and for replays and
+    /// benchmarks, which aren't real runs
+    save: bool,
+    /// Set when the records change so the UI is redrawn
+    changed: bool,
+}
+
+impl Leaderboard {
+    fn records(&self) -> &[Record] {
+        self.records
+            .levels
+            .get(&self.map)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Add the finished run to the table, saving its replay if it has one and made it
+    fn end_run(&mut self, score: &Score, seed: u64) {
+        let mut frames = self.frames.take();
+        if !self.save || score.secs < MIN_RECORD_SECS {
+            return;
+        }
+        // The last frame still counts towards the score but must not reset the level, so the
+        // replay ends on the run's final state
+        if let Some(frame) = frames.as_mut().and_then(|frames| frames.last_mut()) {
+            frame.action.reset = false;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let stem = Path::new(&self.map)
+            .file_stem()
+            .map_or_else(|| self.map.clone(), |s| s.to_string_lossy().into_owned());
+        let record = Record {
+            score: score.total(),
+            secs: score.secs,
+            near_misses: score.near_misses,
+            seed,
+            replay: frames.is_some().then(|| format!("{stem}-{timestamp}.ron")),
+            timestamp,
+        };
+        let replay_dir = self.dir.join(REPLAY_DIR);
+        let replay_path = record.replay.as_ref().map(|replay| replay_dir.join(replay));
+
+        let (rank, dropped) = self.records.insert(&self.map, record);
+        for replay in dropped.into_iter().filter_map(|record| record.replay) {
+            // The new record's replay was never written if it didn't make the table
+            let _ = std::fs::remove_file(replay_dir.join(replay));
+        }
+        let Some(rank) = rank else {
+            return;
+        };
+        info!("New record #{} on {}", rank + 1, self.map);
+        if let (Some(frames), Some(replay_path)) = (frames, replay_path) {
+            let replay = Replay {
+                map: self.map.clone(),
+                seed,
+                config: self.config.take(),
+                frames,
+            };
+            if let Err(e) = std::fs::create_dir_all(&replay_dir)
+                .map_err(|e| e.to_string())
+                .and_then(|()| replay.save(&replay_path))
+            {
+                error!("Failed to save replay {:?}: {e}", replay_path);
+            }
+        }
+        let path = self.dir.join(SAVE_FILE);
+        if let Err(e) = self.records.save(&path) {
+            error!("Failed to save leaderboard {:?}: {e}", path);
+        }
+        self.changed = true;
+    }
+}
+
+fn load_leaderboard(
+    mut commands: Commands,
+    args: Res<Args>,
+    config: Res<Config>,
+    registry: Res<AppTypeRegistry>,
+) {
+    let Some(map) = args.map.clone() else {
+        return;
+    };
+    let Some(dir) = save_dir() else {
+        warn!("No data directory, records won't be saved");
+        return;
+    };
+    let path = dir.join(SAVE_FILE);
+    let (records, readable) = if path.exists() {
+        match SaveFile::load(&path) {
+            Ok(records) => (records, true),
+            Err(e) => {
+                error!(
+                    "Failed to load leaderboard {:?}: {e}. Records won't be saved",
+                    path
+                );
+                (SaveFile::default(), false)
+            }
+        }
+    } else {
+        (SaveFile::default(), true)
+    };
+    commands.insert_resource(Leaderboard {
+        dir,
+        map,
+        records,
+        frames: Some(vec![]),
+        config: recorded_config(&config, &registry),
+        save: readable && args.replay.is_none() && args.bench.is_none(),
+        changed: true,
+    });
+}
+
+fn record_frame(
+    time: Res<Time<Real>>,
+    action: Res<PlayerAction>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    if let Some(frames) = leaderboard.frames.as_mut() {
+        frames.push(ReplayFrame {
+            delta: time.delta(),
+            action: *action,
+        });
+    }
+}
+
+/// Runs end when the level resets. Quitting mid-run still counts.
+fn end_run(
+    rng: Res<GameRng>,
+    score: Res<Score>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut run_over_events: EventReader<RunOverEvent>,
+    mut exit_events: EventReader<AppExit>,
+) {
+    if let Some(event) = run_over_events.read().last() {
+        leaderboard.end_run(&event.score, rng.seed());
+    }
+    if exit_events.read().last().is_some() {
+        leaderboard.end_run(&score, rng.seed());
+    }
+}
+
+/// Top records of the current level
+#[derive(Component)]
+struct LeaderboardUi;
+
+fn spawn_leaderboard_ui(mut commands: Commands) {
+    commands.spawn((
+        LeaderboardUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: LEADERBOARD_UI_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        })
+        .with_text_justify(JustifyText::Right),
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_leaderboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut q_ui: Query<&mut Visibility, With<LeaderboardUi>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    for mut visibility in q_ui.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_leaderboard_ui(
+    mut leaderboard: ResMut<Leaderboard>,
+    mut q_text: Query<&mut Text, With<LeaderboardUi>>,
+) {
+    if !leaderboard.changed {
+        return;
+    }
+    leaderboard.changed = false;
+    let mut value = format!("{} best", leaderboard.map);
+    if leaderboard.records().is_empty() {
+        value.push_str("\nno records yet");
+    }
+    for (i, record) in leaderboard.records().iter().enumerate() {
+        value.push_str(&format!(
+            "\n{}. {}  {:.1}s  seed {}",
+            i + 1,
+            record.score,
+            record.secs,
+            record.seed
+        ));
+    }
+    for mut text in q_text.iter_mut() {
+        text.sections[0].value.clone_from(&value);
+    }
+}
@@ -0,0 +1,97 @@
+//! Levels played headlessly with scripted input. `assets/levels/fixture.tmx` is a walled room
+//! split by a wall with a gap at the bottom. The player starts left of a spawner trigger that
+//! activates a spawner on the far side of the wall.
+
+use bevy::prelude::*;
+
+use crate::{config::Config, harness::TestLevel, player::PlayerAction, score::Score};
+
+const FIXTURE: &str = "fixture.tmx";
+const TRIGGER_ID: u32 = 2;
+const SPAWNER_ID: u32 = 3;
+
+/// Load the fixture, walk right into the spawner trigger and stop
+fn walk_into_trigger() -> TestLevel {
+    let mut level = TestLevel::load(FIXTURE);
+    let walk_right = PlayerAction {
+        move_dir: Vec2::X,
+        ..default()
+    };
+    level.run_script(&[(30, walk_right), (10, PlayerAction::default())]);
+    level
+}
+
+#[test]
+fn spawner_waits_for_trigger() {
+    let mut level = TestLevel::load(FIXTURE);
+    level.run(120);
+    level.assert_spawner_emitted(SPAWNER_ID, 0);
+    assert!(level.chaser_positions().is_empty());
+}
+
+#[test]
+fn trigger_fires_when_player_enters() {
+    let mut level = walk_into_trigger();
+    level.assert_trigger_fired(TRIGGER_ID);
+}
+
+#[test]
+fn spawner_emits_num_spawn_chasers() {
+    let mut level = walk_into_trigger();
+    level.run(180);
+    let num_spawn = level.spawner(SPAWNER_ID).num_spawn;
+    level.assert_spawner_emitted(SPAWNER_ID, num_spawn);
+    assert_eq!(level.chaser_positions().len(), num_spawn as usize);
+}
+
+#[test]
+fn chasers_reach_player() {
+    let mut level = walk_into_trigger();
+    level.assert_chasers_reach_player(10.0);
+}
+
+#[test]
+fn chasers_stay_out_of_walls() {
+    let mut level = walk_into_trigger();
+    for _ in 0..20 {
+        level.run(30);
+        level.assert_no_chaser_in_wall();
+    }
+}
+
+#[test]
+fn same_seed_plays_out_the_same() {
+    let mut first = walk_into_trigger();
+    let mut second = walk_into_trigger();
+    first.run(300);
+    second.run(300);
+    assert_eq!(first.chaser_positions(), second.chaser_positions());
+}
+
+#[test]
+fn replay_reproduces_score() {
+    let mut level = TestLevel::load(FIXTURE);
+    // Fewer than the spawner's 3, so the replay only matches if it brings its config
+    level.world().resource_mut::<Config>().max_chasers = 2;
+    let walk_right = PlayerAction {
+        move_dir: Vec2::X,
+        ..default()
+    };
+    level.run_script(&[(30, walk_right), (10, PlayerAction::default())]);
+    level.run(300);
+    let score = level.world().resource::<Score>().total();
+    assert!(score > 0);
+    assert_eq!(level.chaser_positions().len(), 2);
+
+    let replay = level.replay();
+    let path = std::env::temp_dir().join(format!("train_game-test-{}.ron", std::process::id()));
+    replay.save(&path).unwrap();
+    let mut playback = TestLevel::load_replay(&path);
+    let _ = std::fs::remove_file(&path);
+    // Loading ran the first frame
+    playback.run(replay.frames.len() as u32 - 1);
+
+    assert_eq!(playback.world().resource::<Score>().total(), score);
+    assert_eq!(playback.player_pos(), level.player_pos());
+    assert_eq!(playback.chaser_positions(), level.chaser_positions());
+}
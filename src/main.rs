@@ -1,22 +1,47 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::too_many_arguments)]
 
+mod ability;
+mod bench;
+mod boss;
 mod camera;
 mod chaser;
 mod config;
 mod cursor;
 mod debug;
 mod debug_overlay;
+mod director;
+mod door;
 mod flowfield;
 // mod framerate;
 mod gamepad;
+mod ghost;
+#[cfg(test)]
+mod harness;
+mod headless;
+mod leaderboard;
+#[cfg(test)]
+mod level_tests;
+mod patrol;
 mod physics;
+mod pickup;
 mod player;
 mod point;
+mod properties;
+mod replay;
+mod rng;
+mod score;
 mod segment;
+mod shooter;
 mod spawner;
 mod tilemap;
+mod train;
+mod trigger;
+mod wagon;
 
+use ability::AbilityPlugin;
+use bench::BenchPlugin;
+use bevy::app::PluginGroupBuilder;
 use bevy::core::FrameCount;
 use bevy::input::common_conditions::input_toggle_active;
 use bevy::prelude::*;
@@ -26,28 +51,90 @@ use bevy_framepace::{FramepaceSettings, Limiter};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_prototype_lyon::plugin::ShapePlugin;
 use bevy_svg::SvgPlugin;
+use boss::BossPlugin;
 use camera::CameraPlugin;
 use chaser::ChaserPlugin;
+use clap::Parser;
 use config::{Config, ConfigPlugin};
 use cursor::CursorPlugin;
 use debug::DebugPlugin;
 use debug_overlay::DebugOverlayPlugin;
+use director::DirectorPlugin;
+use door::DoorPlugin;
 use flowfield::FlowfieldPlugin;
 use gamepad::GamepadPlugin;
+use ghost::GhostPlugin;
+use headless::HeadlessPlugin;
+use leaderboard::LeaderboardPlugin;
+use patrol::PatrolPlugin;
 use physics::PhysicsPlugin;
+use pickup::PickupPlugin;
 use player::PlayerPlugin;
+use replay::ReplayPlugin;
+use rng::RngPlugin;
+use score::ScorePlugin;
+use shooter::ShooterPlugin;
 use spawner::SpawnPlugin;
-use tilemap::MyTilemapPlugin;
+use tilemap::{Args, MyTilemapPlugin};
+use train::TrainPlugin;
+use trigger::TriggerPlugin;
+use wagon::WagonPlugin;
 
 const BACKGROUND_COLOR: Color = Color::srgb(0.05, 0.065, 0.08);
 const WINDOW_WIDTH: f32 = 3440.;
 const WINDOW_HEIGHT: f32 = 1361.;
 
 fn main() {
+    let args = Args::parse();
+    let mut app = App::new();
+
+    if args.headless {
+        app.add_plugins(HeadlessPlugin { ticks: args.ticks });
+    } else {
+        add_window_plugins(&mut app);
+    }
+
+    app.insert_resource(args).add_plugins(GameplayPlugins).run();
+}
+
+/// Everything that makes up the game itself. Runs with or without a window.
+struct GameplayPlugins;
+
+impl PluginGroup for GameplayPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(ConfigPlugin)
+            .add(CursorPlugin)
+            .add(FlowfieldPlugin)
+            .add(GamepadPlugin)
+            .add(PhysicsPlugin)
+            .add(RngPlugin)
+            .add(SpawnPlugin)
+            .add(DirectorPlugin)
+            .add(TriggerPlugin)
+            .add(DoorPlugin)
+            .add(PickupPlugin)
+            .add(MyTilemapPlugin)
+            .add(ReplayPlugin)
+            .add(BenchPlugin)
+            .add(PlayerPlugin)
+            .add(ChaserPlugin)
+            .add(PatrolPlugin)
+            .add(ShooterPlugin)
+            .add(TrainPlugin)
+            .add(WagonPlugin)
+            .add(BossPlugin)
+            .add(AbilityPlugin)
+            .add(ScorePlugin)
+    }
+}
+
+/// Window, rendering and debug UI
+fn add_window_plugins(app: &mut App) {
     let window_resolution =
         WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT).with_scale_factor_override(1.0);
 
-    App::new()
+    app
         // Bevy built-ins
         .add_plugins(
             DefaultPlugins
@@ -75,19 +162,12 @@ fn main() {
         // Bevy inspector egui
         .add_plugins(WorldInspectorPlugin::new().run_if(input_toggle_active(false, KeyCode::KeyX)))
         // User plugins
-        .add_plugins(ConfigPlugin)
-        .add_plugins(CursorPlugin)
         .add_plugins(CameraPlugin)
-        .add_plugins(FlowfieldPlugin)
-        .add_plugins(GamepadPlugin)
-        .add_plugins(PhysicsPlugin)
-        .add_plugins(SpawnPlugin)
-        .add_plugins(MyTilemapPlugin)
+        .add_plugins(GhostPlugin)
+        .add_plugins(LeaderboardPlugin)
         .add_plugins(DebugPlugin)
         // .add_plugins(FrametimePlugin)
         .add_plugins(DebugOverlayPlugin)
-        .add_plugins(PlayerPlugin)
-        .add_plugins(ChaserPlugin)
         .add_systems(Startup, setup_window)
         .add_systems(
             Update,
@@ -98,8 +178,7 @@ fn main() {
                 exit_app,
             ),
         )
-        .insert_resource(ClearColor(BACKGROUND_COLOR))
-        .run();
+        .insert_resource(ClearColor(BACKGROUND_COLOR));
 }
 
 fn exit_app(keyboard: Res<ButtonInput<KeyCode>>, mut exit: EventWriter<AppExit>) {
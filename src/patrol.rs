@@ -0,0 +1,244 @@
+use bevy::{math::vec2, prelude::*, utils::hashbrown::HashMap};
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::PI;
+use tiled::{ObjectData, ObjectShape};
+
+use crate::{
+    ability::Slowed,
+    chaser::{update_chaser_avoidance, Chaser},
+    physics::SimulationSet,
+    player::Player,
+    properties::object_properties,
+};
+
+/// Chasers are considered to have reached a waypoint when they are within this distance of it
+const WAYPOINT_RADIUS: f32 = 4.0;
+
+/// Lets spawned chasers follow paths authored in Tiled
+pub struct PatrolPlugin;
+
+impl Plugin for PatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            steer_patrollers
+                .before(update_chaser_avoidance)
+                .in_set(SimulationSet),
+        )
+        .register_type::<PatrolPath>()
+        .register_type::<Patroller>();
+    }
+}
+
+/// Route made from a polyline or polygon object in Tiled.
+#[derive(Component, Default, Reflect, Debug)]
+pub struct PatrolPath {
+    /// object id of this path, referenced by spawners
+    pub id: u32,
+    /// Waypoints in world coords
+    pub points: Vec<Vec2>,
+    /// Path was drawn as a polygon
+    pub closed: bool,
+    /// Speed chasers travel along the path
+    pub speed: f32,
+    /// Turn around at the ends of the path instead of looping back to the first waypoint.
+    /// Always set for open paths.
+    pub ping_pong: bool,
+    /// Seconds to wait at each waypoint
+    pub wait: f32,
+    /// Chasers stop patrolling once the player is this close. 0.0 means never.
+    pub aggro_radius: f32,
+}
+
+object_properties! {
+    /// Custom properties of a [`PatrolPath`] object
+    struct PatrolPathProperties {
+        speed: f32,
+        ping_pong: bool,
+        wait: f32,
+        aggro_radius: f32 = 0.0,
+    }
+}
+
+impl PatrolPath {
+    /// `origin` is the object position in world coords.
+    pub fn from_object(object_data: &ObjectData, origin: Vec2) -> Self {
+        let (points, closed) = match &object_data.shape {
+            ObjectShape::Polyline { points } => (points, false),
+            ObjectShape::Polygon { points } => (points, true),
+            _ => panic!(
+                "Patrol path shape not supported. Object ID: {}",
+                object_data.id()
+            ),
+        };
+        assert!(
+            points.len() >= 2,
+            "Patrol path needs at least 2 points. Object ID: {}",
+            object_data.id()
+        );
+
+        // Points are relative to the object position and y points down in Tiled
+        let rotation = Rot2::degrees(-object_data.rotation);
+        let points = points
+            .iter()
+            .map(|&(x, y)| origin + rotation * vec2(x, -y))
+            .collect();
+
+        let properties = PatrolPathProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        // Looping an open path would go straight from the last point to the first, possibly
+        // through walls
+        if !closed && !properties.ping_pong {
+            warn!(
+                "Open patrol path {} can't loop, it ping pongs instead. Draw a polygon to loop.",
+                object_data.id()
+            );
+        }
+
+        PatrolPath {
+            id: object_data.id(),
+            points,
+            closed,
+            speed: properties.speed,
+            ping_pong: properties.ping_pong || !closed,
+            wait: properties.wait,
+            aggro_radius: properties.aggro_radius,
+        }
+    }
+
+    /// Index of the waypoint closest to `pos`
+    pub fn nearest_waypoint(&self, pos: Vec2) -> usize {
+        self.points
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// Chaser that follows a [`PatrolPath`] instead of the flowfield.
+#[derive(Component, Debug, Reflect)]
+pub struct Patroller {
+    /// Which path this chaser follows
+    pub path_id: u32,
+    /// Index of the waypoint being travelled to. Starts at the nearest waypoint.
+    waypoint: Option<usize>,
+    /// Travelling backwards along a ping pong path
+    reverse: bool,
+    /// Set while waiting at a waypoint
+    wait_timer: Option<Timer>,
+}
+
+impl Patroller {
+    pub fn new(path_id: u32) -> Self {
+        Patroller {
+            path_id,
+            waypoint: None,
+            reverse: false,
+            wait_timer: None,
+        }
+    }
+
+    /// Move on to the next waypoint
+    fn advance(&mut self, path: &PatrolPath) {
+        let Some(waypoint) = self.waypoint else {
+            return;
+        };
+        let last = path.points.len() - 1;
+
+        let next = if path.ping_pong {
+            if (self.reverse && waypoint == 0) || (!self.reverse && waypoint == last) {
+                self.reverse = !self.reverse;
+            }
+            if self.reverse {
+                waypoint - 1
+            } else {
+                waypoint + 1
+            }
+        } else {
+            (waypoint + 1) % path.points.len()
+        };
+        self.waypoint = Some(next);
+    }
+}
+
+/// Steer patrolling chasers along their path. The flowfield force is not applied to these chasers.
+fn steer_patrollers(
+    mut commands: Commands,
+    time: Res<Time>,
+    q_paths: Query<&PatrolPath>,
+    q_player: Query<&Transform, (With<Player>, Without<Chaser>)>,
+    mut q_chasers: Query<
+        (
+            Entity,
+            &mut Patroller,
+            &mut ExternalForce,
+            &mut Transform,
+            &Damping,
+            Option<&Slowed>,
+            &ReadMassProperties,
+        ),
+        With<Chaser>,
+    >,
+) {
+    let paths = q_paths.iter().map(|p| (p.id, p)).collect::<HashMap<_, _>>();
+    let player_pos = q_player.get_single().ok().map(|t| t.translation.xy());
+
+    for (entity, mut patroller, mut force, mut transform, damping, slowed, read_mass) in
+        q_chasers.iter_mut()
+    {
+        let pos = transform.translation.xy();
+
+        // Chaser is not following a valid path so let the flowfield take over
+        let Some(&path) = paths.get(&patroller.path_id) else {
+            commands.entity(entity).remove::<Patroller>();
+            continue;
+        };
+
+        // Start chasing player if they get too close
+        if let Some(player_pos) = player_pos {
+            if path.aggro_radius > 0.0 && pos.distance(player_pos) < path.aggro_radius {
+                commands.entity(entity).remove::<Patroller>();
+                continue;
+            }
+        }
+
+        force.force = Vec2::ZERO;
+
+        // Wait at waypoint
+        if let Some(timer) = patroller.wait_timer.as_mut() {
+            timer.tick(time.delta());
+            if !timer.finished() {
+                continue;
+            }
+            patroller.wait_timer = None;
+            patroller.advance(path);
+        }
+
+        let waypoint = *patroller
+            .waypoint
+            .get_or_insert_with(|| path.nearest_waypoint(pos));
+
+        let diff = path.points[waypoint] - pos;
+        if diff.length_squared() < WAYPOINT_RADIUS * WAYPOINT_RADIUS {
+            if path.wait > 0.0 {
+                patroller.wait_timer = Some(Timer::from_seconds(path.wait, TimerMode::Once));
+            } else {
+                patroller.advance(path);
+            }
+            continue;
+        }
+        let Ok(dir) = Dir2::new(diff) else {
+            continue;
+        };
+
+        // Force needed for linear damping to cap velocity at the path speed. Uses the damping from
+        // before any slow field, so the field's extra damping slows the chaser down.
+        let base_damping = slowed.map_or(damping.linear_damping, |s| s.damping);
+        force.force = dir * path.speed * base_damping * read_mass.mass;
+
+        // Update rotation to face the direction of travel
+        let target = Quat::from_rotation_z(dir.to_angle() - 0.75 * PI);
+        let s = 6.0 * time.delta_seconds();
+        transform.rotation = transform.rotation.lerp(target, s);
+    }
+}
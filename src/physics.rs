@@ -1,24 +1,53 @@
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::{plugin::PhysicsSet, prelude::*};
 
 use crate::config::DebugViews;
 
-/// Add rapier physics
+/// Gameplay and physics steps per second. Rendering is interpolated between steps, so this
+/// doesn't depend on the framerate.
+const SIMULATION_HZ: f64 = 120.0;
+
+/// Add rapier physics on a fixed timestep. Together with the `enhanced-determinism` feature this
+/// makes runs with the same seed and inputs play out the same.
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-            .add_plugins(RapierDebugRenderPlugin::default().disabled())
-            .add_systems(Startup, disable_gravity)
-            .add_systems(Update, toggle_rapier_debug);
+        app.add_plugins(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).in_fixed_schedule(),
+        )
+        .add_plugins(RapierDebugRenderPlugin::default().disabled())
+        .insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+        .insert_resource(TimestepMode::Fixed {
+            dt: (1.0 / SIMULATION_HZ) as f32,
+            substeps: 1,
+        })
+        .add_systems(Startup, disable_gravity)
+        .add_systems(PreUpdate, restore_physics_transforms)
+        .add_systems(Update, (add_interpolation, toggle_rapier_debug))
+        .add_systems(
+            FixedPostUpdate,
+            record_physics_transforms.after(PhysicsSet::Writeback),
+        )
+        .add_systems(
+            PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        );
     }
 }
 
+/// Gameplay systems that run in `FixedUpdate` before each physics step, which rapier runs in
+/// `FixedPostUpdate`, so they behave the same at any framerate
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationSet;
+
 pub const WALL_GROUP: Group = Group::GROUP_1;
 pub const TRIGGER_GROUP: Group = Group::GROUP_2;
 pub const PLAYER_GROUP: Group = Group::GROUP_3;
 pub const CHASER_GROUP: Group = Group::GROUP_4;
+pub const PROJECTILE_GROUP: Group = Group::GROUP_5;
+pub const WAGON_GROUP: Group = Group::GROUP_6;
+pub const PICKUP_GROUP: Group = Group::GROUP_7;
 
 fn disable_gravity(mut rapier_config: ResMut<RapierConfiguration>) {
     rapier_config.gravity = Vect::ZERO;
@@ -31,3 +60,81 @@ fn toggle_rapier_debug(
 ) {
     debug_render_context.enabled = config.render_rapier;
 }
+
+/// Transform of a moving body after the last two physics steps. The rendered transform is
+/// blended between them. Added to dynamic and velocity-based kinematic bodies. Anything else
+/// moved in `FixedUpdate` can add it too, and gameplay code that teleports a body mid-step
+/// inserts a new one so the jump isn't blended.
+#[derive(Component, Debug)]
+pub struct InterpolatedTransform {
+    previous: (Vec3, Quat),
+    current: (Vec3, Quat),
+    /// Last value written to the transform here. Anything else was set by gameplay code and
+    /// is kept.
+    rendered: (Vec3, Quat),
+}
+
+impl InterpolatedTransform {
+    pub fn new(transform: &Transform) -> Self {
+        let state = (transform.translation, transform.rotation);
+        InterpolatedTransform {
+            previous: state,
+            current: state,
+            rendered: state,
+        }
+    }
+}
+
+fn add_interpolation(
+    mut commands: Commands,
+    q_bodies: Query<(Entity, &RigidBody, &Transform), Added<RigidBody>>,
+) {
+    for (entity, body, transform) in q_bodies.iter() {
+        if matches!(body, RigidBody::Dynamic | RigidBody::KinematicVelocityBased) {
+            commands
+                .entity(entity)
+                .insert(InterpolatedTransform::new(transform));
+        }
+    }
+}
+
+/// Put bodies back where physics left them, so gameplay code sees the simulated transform.
+/// Transforms that were teleported since the last frame are kept and not blended.
+fn restore_physics_transforms(mut q_bodies: Query<(&mut Transform, &mut InterpolatedTransform)>) {
+    for (mut transform, mut interpolated) in q_bodies.iter_mut() {
+        if (transform.translation, transform.rotation) == interpolated.rendered {
+            (transform.translation, transform.rotation) = interpolated.current;
+            interpolated.rendered = interpolated.current;
+        } else {
+            *interpolated = InterpolatedTransform::new(&transform);
+        }
+    }
+}
+
+fn record_physics_transforms(mut q_bodies: Query<(&Transform, &mut InterpolatedTransform)>) {
+    for (transform, mut interpolated) in q_bodies.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = (transform.translation, transform.rotation);
+        interpolated.rendered = interpolated.current;
+    }
+}
+
+/// Blend between the last two physics steps by how far into the next step the frame is
+pub fn interpolate_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    mut q_bodies: Query<(&mut Transform, &mut InterpolatedTransform)>,
+) {
+    let s = fixed_time.overstep_fraction();
+    for (mut transform, mut interpolated) in q_bodies.iter_mut() {
+        // Teleported after the last step
+        if (transform.translation, transform.rotation) != interpolated.rendered {
+            *interpolated = InterpolatedTransform::new(&transform);
+            continue;
+        }
+        let (previous_translation, previous_rotation) = interpolated.previous;
+        let (translation, rotation) = interpolated.current;
+        transform.translation = previous_translation.lerp(translation, s);
+        transform.rotation = previous_rotation.slerp(rotation, s);
+        interpolated.rendered = (transform.translation, transform.rotation);
+    }
+}
@@ -0,0 +1,351 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+use tiled::ObjectData;
+
+use crate::{
+    chaser::Chaser,
+    config::Config,
+    physics::{SimulationSet, PICKUP_GROUP, PLAYER_GROUP},
+    player::{Player, ResetEvent},
+    properties::{object_properties, property_enum},
+};
+
+const PICKUP_RADIUS: f32 = 10.0;
+const PICKUP_Z: f32 = 3.0;
+const PICKUP_UI_COLOR: Color = Color::srgb(0.6, 1.0, 0.6);
+
+/// Pickups placed in Tiled and the timed effects they give the player
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_pickup_text)
+            .add_systems(
+                FixedUpdate,
+                (
+                    prepare_pickups,
+                    reset_pickups,
+                    collect_pickups,
+                    update_effects,
+                    freeze_chasers,
+                )
+                    .chain()
+                    .in_set(SimulationSet),
+            )
+            .add_systems(Update, update_pickup_ui)
+            .init_resource::<ActiveEffects>()
+            .register_type::<Pickup>();
+    }
+}
+
+property_enum! {
+    #[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PickupKind {
+        /// Multiplies the player's max speeds
+        SpeedBoost,
+        /// The player ignores hits
+        Shield,
+        /// Chasers can't move
+        Freeze,
+        /// Despawns chasers near the pickup. Not timed.
+        Bomb,
+        /// Not timed
+        ExtraLife,
+    }
+}
+
+impl PickupKind {
+    fn color(self) -> Color {
+        match self {
+            PickupKind::SpeedBoost => Color::srgb(1.0, 0.85, 0.1),
+            PickupKind::Shield => Color::srgb(0.3, 0.6, 1.0),
+            PickupKind::Freeze => Color::srgb(0.7, 0.95, 1.0),
+            PickupKind::Bomb => Color::srgb(1.0, 0.25, 0.2),
+            PickupKind::ExtraLife => Color::srgb(0.3, 1.0, 0.4),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PickupKind::SpeedBoost => "speed",
+            PickupKind::Shield => "shield",
+            PickupKind::Freeze => "freeze",
+            PickupKind::Bomb => "bomb",
+            PickupKind::ExtraLife => "life",
+        }
+    }
+}
+
+object_properties! {
+    /// Custom properties of a [`Pickup`] object
+    struct PickupProperties {
+        kind: PickupKind,
+        /// Seconds a timed effect lasts
+        duration: f32 = 5.0,
+        /// Speed boost multiplier
+        multiplier: f32 = 1.5,
+        /// Bomb radius
+        radius: f32 = 200.0,
+    }
+}
+
+/// Collected when the player touches it. Comes back when the level is reset.
+#[derive(Component, Debug, Reflect)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    duration: f32,
+    multiplier: f32,
+    radius: f32,
+    collected: bool,
+}
+
+impl Pickup {
+    pub fn from_object(object_data: &ObjectData) -> Self {
+        let properties = PickupProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        Pickup {
+            kind: properties.kind,
+            duration: properties.duration,
+            multiplier: properties.multiplier,
+            radius: properties.radius,
+            collected: false,
+        }
+    }
+}
+
+/// Timed effect from a pickup
+#[derive(Debug)]
+struct Effect {
+    kind: PickupKind,
+    timer: Timer,
+}
+
+/// Effects that are running. Collecting a pickup of an active kind restarts its timer.
+#[derive(Resource, Debug, Default)]
+struct ActiveEffects(Vec<Effect>);
+
+impl ActiveEffects {
+    fn is_active(&self, kind: PickupKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+}
+
+/// Undo what an effect did to the player
+fn end_effect(effect: &Effect, player: &mut Player) {
+    match effect.kind {
+        PickupKind::SpeedBoost => player.speed_multiplier = 1.0,
+        PickupKind::Shield => player.shielded = false,
+        _ => (),
+    }
+}
+
+/// Add visuals and a sensor to pickups loaded from the tilemap
+fn prepare_pickups(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_pickups: Query<(Entity, &Pickup, &Transform), Added<Pickup>>,
+) {
+    for (entity, pickup, transform) in q_pickups.iter() {
+        commands.entity(entity).insert((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(PICKUP_RADIUS))),
+                material: materials.add(ColorMaterial::from_color(pickup.kind.color())),
+                transform: transform.with_translation(transform.translation.with_z(PICKUP_Z)),
+                ..default()
+            },
+            Collider::ball(PICKUP_RADIUS),
+            Sensor,
+            CollisionGroups::new(PICKUP_GROUP, PLAYER_GROUP),
+            ActiveEvents::COLLISION_EVENTS,
+            Name::new("Pickup"),
+        ));
+    }
+}
+
+/// Bring back collected pickups and end all effects
+fn reset_pickups(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    mut effects: ResMut<ActiveEffects>,
+    mut q_pickups: Query<(Entity, &mut Pickup)>,
+    mut q_player: Query<&mut Player>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for (entity, mut pickup) in q_pickups.iter_mut() {
+        pickup.collected = false;
+        commands
+            .entity(entity)
+            .remove::<ColliderDisabled>()
+            .insert(Visibility::Visible);
+    }
+    if let Ok(mut player) = q_player.get_single_mut() {
+        for effect in effects.0.drain(..) {
+            end_effect(&effect, &mut player);
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut effects: ResMut<ActiveEffects>,
+    mut q_pickups: Query<(Entity, &mut Pickup, &Transform)>,
+    mut q_player: Query<(Entity, &mut Player)>,
+    q_chasers: Query<(Entity, &Transform), With<Chaser>>,
+) {
+    let Ok((player_entity, mut player)) = q_player.get_single_mut() else {
+        return;
+    };
+    for event in collision_events.read() {
+        let &CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        let entity = if collider1 == player_entity {
+            collider2
+        } else if collider2 == player_entity {
+            collider1
+        } else {
+            continue;
+        };
+        let Ok((entity, mut pickup, transform)) = q_pickups.get_mut(entity) else {
+            continue;
+        };
+        if pickup.collected {
+            continue;
+        }
+        pickup.collected = true;
+        commands
+            .entity(entity)
+            .insert((ColliderDisabled, Visibility::Hidden));
+
+        match pickup.kind {
+            PickupKind::Bomb => {
+                let pos = transform.translation.xy();
+                for (chaser, chaser_transform) in q_chasers.iter() {
+                    if chaser_transform.translation.xy().distance(pos) < pickup.radius {
+                        commands.entity(chaser).despawn_recursive();
+                    }
+                }
+            }
+            // Lives aren't counted when they are turned off
+            PickupKind::ExtraLife if config.player_lives == 0 => (),
+            PickupKind::ExtraLife => player.lives += 1,
+            kind => {
+                if let Some(effect) = effects.0.iter_mut().find(|effect| effect.kind == kind) {
+                    effect.timer = Timer::from_seconds(pickup.duration, TimerMode::Once);
+                    continue;
+                }
+                match kind {
+                    PickupKind::SpeedBoost => player.speed_multiplier = pickup.multiplier,
+                    PickupKind::Shield => player.shielded = true,
+                    _ => (),
+                }
+                effects.0.push(Effect {
+                    kind,
+                    timer: Timer::from_seconds(pickup.duration, TimerMode::Once),
+                });
+            }
+        }
+    }
+}
+
+fn update_effects(
+    time: Res<Time>,
+    mut effects: ResMut<ActiveEffects>,
+    mut q_player: Query<&mut Player>,
+) {
+    let Ok(mut player) = q_player.get_single_mut() else {
+        return;
+    };
+    effects.0.retain_mut(|effect| {
+        effect.timer.tick(time.delta());
+        if effect.timer.finished() {
+            end_effect(effect, &mut player);
+        }
+        !effect.timer.finished()
+    });
+}
+
+/// Marks chasers held in place by a freeze effect
+#[derive(Component)]
+struct Frozen;
+
+/// Lock chasers in place while a freeze effect is active, including chasers spawned during it
+fn freeze_chasers(
+    mut commands: Commands,
+    effects: Res<ActiveEffects>,
+    q_chasers: Query<Entity, (With<Chaser>, Without<Frozen>)>,
+    q_frozen: Query<Entity, With<Frozen>>,
+) {
+    if effects.is_active(PickupKind::Freeze) {
+        for entity in q_chasers.iter() {
+            commands
+                .entity(entity)
+                .insert((Frozen, LockedAxes::TRANSLATION_LOCKED));
+        }
+    } else {
+        for entity in q_frozen.iter() {
+            commands.entity(entity).remove::<(Frozen, LockedAxes)>();
+        }
+    }
+}
+
+/// Draws lives and active effects on screen
+#[derive(Component)]
+struct PickupUi;
+
+fn spawn_pickup_text(mut commands: Commands) {
+    commands.spawn((
+        PickupUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: PICKUP_UI_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(110.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_pickup_ui(
+    config: Res<Config>,
+    effects: Res<ActiveEffects>,
+    q_player: Query<&Player>,
+    mut text_query: Query<&mut Text, With<PickupUi>>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let lives = (config.player_lives > 0).then(|| format!("lives {}", player.lives));
+    let timers = effects.0.iter().map(|effect| {
+        format!(
+            "{} {:.0}",
+            effect.kind.label(),
+            effect.timer.remaining_secs().ceil()
+        )
+    });
+    let value = lives
+        .into_iter()
+        .chain(timers)
+        .collect::<Vec<_>>()
+        .join("  ");
+
+    // Avoid updating text layout every frame
+    let mut text = text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
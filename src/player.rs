@@ -2,32 +2,37 @@ use std::{f32::consts::PI, time::Duration};
 
 use bevy::{
     color::palettes::css::*,
-    core::FrameCount,
+    input::InputSystem,
     math::vec2,
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     time::Stopwatch,
 };
+use bevy_inspector_egui::prelude::*;
 use bevy_rapier2d::{dynamics::Velocity, prelude::*};
 use bevy_svg::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     camera::{CameraRange, MainCamera},
     chaser::Chaser,
     config::Config,
     gamepad::MyGamepad,
-    physics::PLAYER_GROUP,
+    physics::{SimulationSet, CHASER_GROUP, PLAYER_GROUP, WALL_GROUP},
     spawner::Spawner,
-    tilemap::{Args, PlayerSpawn},
+    tilemap::PlayerSpawn,
 };
 
 const AVERAGE_SPEED_INTERVAL: f32 = 0.08;
 const COLOR_TRANSITION_TIME: f32 = 0.4;
-const PLAYER_RADIUS: f32 = 8.0;
+pub const PLAYER_RADIUS: f32 = 8.0;
 
 const DAMPING: f32 = 0.0;
-const PLAYER_COLOR: Srgba = Srgba::rgb(3.0 / 255.0, 221.0 / 255.0, 1.0);
+pub const PLAYER_COLOR: Srgba = Srgba::rgb(3.0 / 255.0, 221.0 / 255.0, 1.0);
 const COLLISION_COLOR: Srgba = RED;
+const BOOST_METER_SIZE: Vec2 = Vec2::new(200.0, 12.0);
+const BOOST_METER_COLOR: Srgba = Srgba::rgb(0.2, 0.8, 1.0);
+const BOOST_METER_COOLDOWN_COLOR: Srgba = GRAY;
 
 pub struct PlayerPlugin;
 
@@ -37,28 +42,54 @@ impl Plugin for PlayerPlugin {
             PostStartup,
             spawn_player.after(TransformSystem::TransformPropagate),
         )
-        .add_systems(Startup, spawn_velocity_text)
+        .add_systems(Startup, (spawn_velocity_text, spawn_boost_meter))
+        .configure_sets(PreUpdate, PlayerInputSet.after(InputSystem))
         .add_systems(
-            PostUpdate,
-            (benchmark, player_movement)
+            PreUpdate,
+            (
+                read_player_action.in_set(PlayerInputSet),
+                queue_actions.after(PlayerInputSet),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                handle_collision_event,
+                handle_player_hits,
+                reset_player,
+                player_movement,
+                end_dash_at_walls,
+            )
                 .chain()
-                .before(RapierTransformPropagateSet),
+                .in_set(SimulationSet),
         )
         .add_systems(
             Update,
             (
-                reset_player,
                 toggle_noclip,
-                handle_collision_event,
                 transition_color,
                 update_velocity_ui,
+                update_boost_meter,
             ),
         )
+        .init_resource::<PlayerAction>()
+        .init_resource::<PendingActions>()
+        .add_event::<ResetEvent>()
+        .add_event::<PlayerHitEvent>()
         .register_type::<Player>();
     }
 }
 
-#[derive(Component, Debug, Reflect)]
+/// Sent when the level is reset so other systems can restore their initial state.
+#[derive(Event)]
+pub struct ResetEvent;
+
+/// Sent when an enemy or hazard hurts the player
+#[derive(Event)]
+pub struct PlayerHitEvent;
+
+#[derive(Component, Debug, Reflect, InspectorOptions)]
+#[reflect(Component, InspectorOptions)]
 pub struct Player {
     /// Also affects cornering.
     pub acceleration: f32,
@@ -71,6 +102,42 @@ pub struct Player {
     pub drag: f32,
     /// Very fast. For debug purposes.
     pub debug_max_speed: f32,
+    /// Drains while boosting and regenerates otherwise
+    pub boost_energy: f32,
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub boost_energy_max: f32,
+    /// Energy used per second of boosting
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub boost_drain: f32,
+    /// Energy regained per second while not boosting
+    #[inspector(min = 0.0, max = 1000.0)]
+    pub boost_regen: f32,
+    /// Seconds before energy regenerates after running out
+    #[inspector(min = 0.0, max = 10.0)]
+    pub boost_cooldown: f32,
+    /// Running after the player ran out of energy. No boosting until it finishes.
+    boost_cooldown_timer: Option<Timer>,
+    /// Boost or debug boost held during the last movement step. Dashes don't count.
+    boosting: bool,
+    /// Speed of a dash
+    #[inspector(min = 0.0, max = 5000.0)]
+    pub dash_speed: f32,
+    /// Seconds a dash lasts. Chasers can't hurt the player and are passed through meanwhile.
+    #[inspector(min = 0.0, max = 2.0)]
+    pub dash_duration: f32,
+    /// Seconds from the start of a dash until the next one
+    #[inspector(min = 0.0, max = 10.0)]
+    pub dash_cooldown: f32,
+    dash: Option<Dash>,
+    dash_cooldown_timer: Option<Timer>,
+    /// The level restarts when the last life is lost. Unused when `Config::player_lives` is 0.
+    pub lives: u32,
+    /// Hits are ignored while shielded
+    pub shielded: bool,
+    /// Scales `max_speed` and `boost_max_speed`. Set by speed boost pickups.
+    pub speed_multiplier: f32,
+    /// Hits don't cost a life until this finishes
+    invulnerable: Timer,
 }
 
 impl Default for Player {
@@ -83,16 +150,137 @@ impl Default for Player {
             boost_max_speed: 480.0,
             drag: 0.1,
             debug_max_speed: 960.0,
+            boost_energy: 100.0,
+            boost_energy_max: 100.0,
+            boost_drain: 40.0,
+            boost_regen: 25.0,
+            boost_cooldown: 1.5,
+            boost_cooldown_timer: None,
+            boosting: false,
+            dash_speed: 1100.0,
+            dash_duration: 0.15,
+            dash_cooldown: 0.8,
+            dash: None,
+            dash_cooldown_timer: None,
+            lives: 0,
+            shielded: false,
+            speed_multiplier: 1.0,
+            invulnerable: Timer::default(),
         }
     }
 }
 
+impl Player {
+    /// Boosting and already faster than `max_speed`. A dash is faster, but isn't a boost.
+    pub fn is_boosting(&self, speed: f32) -> bool {
+        self.boosting && speed > self.max_speed * self.speed_multiplier
+    }
+
+    /// Boosting is possible with some energy left and no cooldown running
+    pub fn can_boost(&self) -> bool {
+        self.boost_energy > 0.0 && self.boost_cooldown_timer.is_none()
+    }
+
+    /// Fraction of energy left, for the HUD
+    fn boost_fraction(&self) -> f32 {
+        if self.boost_energy_max > 0.0 {
+            (self.boost_energy / self.boost_energy_max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Drain energy while boosting, otherwise regenerate once the cooldown is over
+    fn update_boost_energy(&mut self, boosting: bool, delta: Duration) {
+        let dt = delta.as_secs_f32();
+        if boosting {
+            self.boost_energy -= self.boost_drain * dt;
+            if self.boost_energy <= 0.0 {
+                self.boost_energy = 0.0;
+                self.boost_cooldown_timer =
+                    Some(Timer::from_seconds(self.boost_cooldown, TimerMode::Once));
+            }
+            return;
+        }
+        if let Some(timer) = &mut self.boost_cooldown_timer {
+            if !timer.tick(delta).finished() {
+                return;
+            }
+            self.boost_cooldown_timer = None;
+        }
+        self.boost_energy = (self.boost_energy + self.boost_regen * dt).min(self.boost_energy_max);
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.dash.is_some()
+    }
+
+    fn can_dash(&self) -> bool {
+        self.dash.is_none() && self.dash_cooldown_timer.is_none()
+    }
+
+    fn start_dash(&mut self, dir: Vec2) {
+        self.dash = Some(Dash {
+            dir,
+            timer: Timer::from_seconds(self.dash_duration, TimerMode::Once),
+        });
+        self.dash_cooldown_timer = Some(Timer::from_seconds(self.dash_cooldown, TimerMode::Once));
+    }
+
+    /// Tick the dash and its cooldown. Returns the dash direction while dashing.
+    fn update_dash(&mut self, delta: Duration) -> Option<Vec2> {
+        if let Some(timer) = &mut self.dash_cooldown_timer {
+            if timer.tick(delta).finished() {
+                self.dash_cooldown_timer = None;
+            }
+        }
+        let dash = self.dash.as_mut()?;
+        if dash.timer.tick(delta).finished() {
+            self.dash = None;
+            return None;
+        }
+        Some(dash.dir)
+    }
+
+    /// Restore full energy
+    fn reset_boost(&mut self) {
+        self.boost_energy = self.boost_energy_max;
+        self.boost_cooldown_timer = None;
+    }
+
+    fn new(config: &Config) -> Self {
+        Player {
+            lives: config.player_lives,
+            invulnerable: Timer::from_seconds(config.player_invulnerable_time, TimerMode::Once),
+            ..default()
+        }
+    }
+}
+
+/// Short burst of speed in a fixed direction
+#[derive(Debug, Reflect)]
+struct Dash {
+    dir: Vec2,
+    timer: Timer,
+}
+
 /// Used for color change on collision
 #[derive(Component, Debug)]
 pub struct TimeSinceCollision {
     time: Stopwatch,
 }
 
+impl TimeSinceCollision {
+    /// Start the color change
+    pub fn reset(&mut self) {
+        self.time.reset();
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.time.elapsed_secs()
+    }
+}
+
 /// Draws average speed of player on screen
 #[derive(Component)]
 pub struct SpeedUi;
@@ -105,6 +293,7 @@ struct AverageSpeed {
 }
 
 pub fn spawn_player(
+    config: Res<Config>,
     q_spawn: Query<&GlobalTransform, With<PlayerSpawn>>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -123,7 +312,7 @@ pub fn spawn_player(
 
     commands
         .spawn((
-            Player::default(),
+            Player::new(&config),
             Name::new("Player"),
             Svg2dBundle {
                 svg,
@@ -171,8 +360,10 @@ pub fn spawn_player(
             ));
         });
 
-    let (mut cam_transform, cam_range) = q_camera.single_mut();
-    cam_transform.translation = spawn_pos.clamp(cam_range.min, cam_range.max);
+    // No camera when running headless
+    if let Ok((mut cam_transform, cam_range)) = q_camera.get_single_mut() {
+        cam_transform.translation = spawn_pos.clamp(cam_range.min, cam_range.max);
+    }
 }
 
 fn spawn_velocity_text(mut commands: Commands) {
@@ -222,17 +413,91 @@ fn update_velocity_ui(
     text.sections[0].value = format!("{0:>6.1}", average_speed);
 }
 
-/// Reset player position, despawn all chasers, and deactivate spawners.
+/// Fill of the boost energy bar
+#[derive(Component)]
+struct BoostMeter;
+
+fn spawn_boost_meter(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(150.0),
+                left: Val::Px(5.0),
+                width: Val::Px(BOOST_METER_SIZE.x),
+                height: Val::Px(BOOST_METER_SIZE.y),
+                ..default()
+            },
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.15).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                BoostMeter,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::from(BOOST_METER_COLOR).into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn update_boost_meter(
+    q_player: Query<&Player>,
+    mut q_meter: Query<(&mut Style, &mut BackgroundColor), With<BoostMeter>>,
+) {
+    let (Ok(player), Ok((mut style, mut color))) =
+        (q_player.get_single(), q_meter.get_single_mut())
+    else {
+        return;
+    };
+    let width = Val::Percent(player.boost_fraction() * 100.0);
+    if style.width != width {
+        style.width = width;
+    }
+    let new_color = if player.boost_cooldown_timer.is_some() {
+        BOOST_METER_COOLDOWN_COLOR
+    } else {
+        BOOST_METER_COLOR
+    };
+    if color.0 != Color::from(new_color) {
+        color.0 = new_color.into();
+    }
+}
+
+/// Reset player position, despawn all chasers, and deactivate spawners. Happens when "R" is
+/// pressed or the player runs out of lives.
 fn reset_player(
     mut commands: Commands,
-    mut player_query: Query<(&mut Transform, &mut ExternalForce, &mut Velocity), With<Player>>,
+    config: Res<Config>,
+    mut player_query: Query<(
+        &mut Transform,
+        &mut ExternalForce,
+        &mut Velocity,
+        &mut Player,
+    )>,
     chaser_query: Query<Entity, With<Chaser>>,
-    k: Res<ButtonInput<KeyCode>>,
+    mut pending: ResMut<PendingActions>,
     q_player_spawn: Query<(&PlayerSpawn, &GlobalTransform)>,
     mut q_spawner: Query<&mut Spawner>,
+    mut reset_events: EventWriter<ResetEvent>,
 ) {
-    if k.just_pressed(KeyCode::KeyR) {
-        let (mut t, mut f, mut v) = player_query.single_mut();
+    let Ok((mut t, mut f, mut v, mut player)) = player_query.get_single_mut() else {
+        return;
+    };
+    let reset = std::mem::take(&mut pending.reset);
+    let out_of_lives = config.player_lives > 0 && player.lives == 0;
+    if reset || out_of_lives {
+        reset_events.send(ResetEvent);
+        player.lives = config.player_lives;
+        player.reset_boost();
+        player.dash = None;
+        player.dash_cooldown_timer = None;
         let (_, spawn_transform) = q_player_spawn.single();
         t.translation = spawn_transform.translation();
         f.force = Vec2::ZERO;
@@ -242,37 +507,11 @@ fn reset_player(
             commands.entity(entity).despawn_recursive();
         }
         for mut spawner in q_spawner.iter_mut() {
-            spawner.timer = if spawner.immediate {
-                Timer::new(Duration::ZERO, TimerMode::Repeating)
-            } else {
-                Timer::from_seconds(spawner.delay, TimerMode::Repeating)
-            };
-            spawner.count = 0;
-            spawner.active = spawner.active_default;
+            spawner.reset();
         }
     }
 }
 
-/// Have consistent benchmarking for tracing
-fn benchmark(
-    args: Res<Args>,
-    framecount: Res<FrameCount>,
-    mut exit: EventWriter<AppExit>,
-    mut keyboard: ResMut<ButtonInput<KeyCode>>,
-) {
-    if !args.bench {
-        return;
-    }
-    // Don't run for too long because the trace files become massive quickly
-    if framecount.0 == 350 {
-        exit.send(AppExit::Success);
-        return;
-    }
-    // Move left fast
-    keyboard.press(KeyCode::ShiftLeft);
-    keyboard.press(KeyCode::ArrowLeft);
-}
-
 /// Let player pass through objects. Toggle with "N" key.
 fn toggle_noclip(
     keyboard: Res<ButtonInput<KeyCode>>,
@@ -291,15 +530,36 @@ fn toggle_noclip(
     }
 }
 
-/// Input gets directly mapped to this.
-#[derive(Clone, Copy, Debug)]
-struct PlayerAction {
+/// Input gets directly mapped to this. Written every frame from devices or a replay.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerAction {
     /// Desired direction of movement.
-    move_dir: Vec2,
+    pub move_dir: Vec2,
     /// Go faster.
-    boost: bool,
+    pub boost: bool,
     /// Go much faster (for debug purposes).
-    debug_boost: bool,
+    pub debug_boost: bool,
+    /// Start a dash. Only set on the frame the button is pressed.
+    pub dash: bool,
+    /// Push chasers away. Only set on the frame the button is pressed.
+    pub shockwave: bool,
+    /// Leave a slow field. Only set on the frame the button is pressed.
+    pub slow_field: bool,
+    /// Restart the level. Only set on the frame the button is pressed.
+    pub reset: bool,
+}
+
+/// Systems in `PreUpdate` that write the frame's [`PlayerAction`]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSet;
+
+/// One-shot actions pressed since the last simulation step. Frames without a step must not
+/// lose them.
+#[derive(Resource, Debug, Default)]
+struct PendingActions {
+    dash: bool,
+    reset: bool,
 }
 
 /// Get [`PlayerAction`] based on user input
@@ -312,6 +572,10 @@ fn get_player_action(
 ) -> PlayerAction {
     let mut boost = false;
     let mut debug_boost = false;
+    let mut dash = false;
+    let mut shockwave = false;
+    let mut slow_field = false;
+    let reset = keyboard.just_pressed(KeyCode::KeyR);
 
     let mut move_dir = Vec2::ZERO;
 
@@ -334,6 +598,15 @@ fn get_player_action(
         if keyboard.pressed(KeyCode::AltLeft) {
             debug_boost = true;
         }
+        if keyboard.just_pressed(KeyCode::Space) {
+            dash = true;
+        }
+        if keyboard.just_pressed(KeyCode::KeyE) {
+            shockwave = true;
+        }
+        if keyboard.just_pressed(KeyCode::KeyQ) {
+            slow_field = true;
+        }
     }
 
     if let Some(&MyGamepad(gamepad)) = gamepad.as_deref() {
@@ -367,34 +640,110 @@ fn get_player_action(
         }) {
             debug_boost = true;
         }
+        if gamepad_buttons.just_pressed(GamepadButton {
+            gamepad,
+            button_type: GamepadButtonType::East,
+        }) {
+            dash = true;
+        }
+        if gamepad_buttons.just_pressed(GamepadButton {
+            gamepad,
+            button_type: GamepadButtonType::West,
+        }) {
+            shockwave = true;
+        }
+        if gamepad_buttons.just_pressed(GamepadButton {
+            gamepad,
+            button_type: GamepadButtonType::North,
+        }) {
+            slow_field = true;
+        }
     }
     PlayerAction {
         move_dir,
         boost,
         debug_boost,
+        dash,
+        shockwave,
+        slow_field,
+        reset,
     }
 }
 
-// TODO fix framerate affecting accel
-fn player_movement(
+pub fn read_player_action(
     config: Res<Config>,
     keyboard: Res<ButtonInput<KeyCode>>,
     gamepad_buttons: Res<ButtonInput<GamepadButton>>,
     axes: Res<Axis<GamepadAxis>>,
-    time: Res<Time>,
     gamepad: Option<Res<MyGamepad>>,
-    mut query: Query<(&mut Velocity, &mut Transform, &Player)>,
+    mut action: ResMut<PlayerAction>,
+) {
+    *action = get_player_action(keyboard, gamepad, axes, gamepad_buttons, config);
+}
+
+fn queue_actions(action: Res<PlayerAction>, mut pending: ResMut<PendingActions>) {
+    pending.dash |= action.dash;
+    pending.reset |= action.reset;
+}
+
+/// Runs on the fixed timestep, so acceleration and drag don't depend on the framerate
+fn player_movement(
+    time: Res<Time>,
+    player_action: Res<PlayerAction>,
+    mut pending: ResMut<PendingActions>,
+    mut query: Query<(
+        &mut Velocity,
+        &mut Transform,
+        &mut Player,
+        &mut CollisionGroups,
+    )>,
 ) {
-    let Ok((mut velocity, mut transform, player)) = query.get_single_mut() else {
+    let Ok((mut velocity, mut transform, mut player, mut groups)) = query.get_single_mut() else {
         return;
     };
 
-    let mut action = get_player_action(keyboard, gamepad, axes, gamepad_buttons, config);
+    let mut action = *player_action;
+    action.dash = std::mem::take(&mut pending.dash);
+
+    // Dash in the input direction, or the direction of travel without input
+    if action.dash && player.can_dash() {
+        let dir = action
+            .move_dir
+            .try_normalize()
+            .or_else(|| velocity.linvel.try_normalize());
+        if let Some(dir) = dir {
+            player.start_dash(dir);
+        }
+    }
+    let dash_dir = player.update_dash(time.delta());
+
+    // Pass through chasers while dashing. Walls still block, and CCD keeps the player from
+    // tunneling through them at dash speed.
+    let filter = if dash_dir.is_some() {
+        Group::ALL - CHASER_GROUP
+    } else {
+        Group::ALL
+    };
+    if groups.filters != filter {
+        groups.filters = filter;
+    }
+
+    // Boosting only uses energy while moving
+    action.boost &= player.can_boost();
+    let boosting =
+        action.boost && !action.debug_boost && dash_dir.is_none() && action.move_dir != Vec2::ZERO;
+    player.update_boost_energy(boosting, time.delta());
+    player.boosting = boosting || (action.debug_boost && dash_dir.is_none());
 
     let v = &mut velocity.linvel;
     let mag_before = v.length();
     let dt = time.delta_seconds();
 
+    if let Some(dir) = dash_dir {
+        *v = dir * player.dash_speed;
+        return;
+    }
+
     // BRAKE
     if action.move_dir == Vec2::ZERO {
         let s = (player.brake_acceleration * dt).min(1.0);
@@ -407,9 +756,15 @@ fn player_movement(
     let (mut max_speed, accel) = if action.debug_boost {
         (player.debug_max_speed, player.boost_acceleration)
     } else if action.boost {
-        (player.boost_max_speed, player.boost_acceleration)
+        (
+            player.boost_max_speed * player.speed_multiplier,
+            player.boost_acceleration,
+        )
     } else {
-        (player.max_speed, player.acceleration)
+        (
+            player.max_speed * player.speed_multiplier,
+            player.acceleration,
+        )
     };
 
     let angle = action.move_dir.to_angle() - PI * 0.75;
@@ -424,16 +779,21 @@ fn player_movement(
     *v = v.lerp(new_velocity, s);
 }
 
-/// If player touches chaser we need to start the timer for color change.
+/// Chasers hurt the player on contact.
 fn handle_collision_event(
     mut contact_events: EventReader<ContactForceEvent>,
-    mut q_stopwatch: Query<&mut TimeSinceCollision>,
+    mut hit_events: EventWriter<PlayerHitEvent>,
     q_chaser: Query<&Chaser>,
+    q_player: Query<(), With<Player>>,
 ) {
     for event in contact_events.read() {
-        if q_chaser.get(event.collider2).is_ok() {
-            // Player is in contact with a chaser! Reset timer to trigger color change
-            q_stopwatch.single_mut().time.reset();
+        // Chasers in trains also produce contact events with each other
+        let (c1, c2) = (event.collider1, event.collider2);
+        if (q_player.contains(c1) && q_chaser.contains(c2))
+            || (q_player.contains(c2) && q_chaser.contains(c1))
+        {
+            // Player is in contact with a chaser!
+            hit_events.send(PlayerHitEvent);
 
             // If multiple chasers are in contact, this ensures that those events are cleared for the next update
             contact_events.clear();
@@ -442,6 +802,64 @@ fn handle_collision_event(
     }
 }
 
+/// Running into a wall ends a dash so the player doesn't keep sliding along it at dash speed.
+fn end_dash_at_walls(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut q_player: Query<(Entity, &mut Player)>,
+    q_groups: Query<&CollisionGroups>,
+) {
+    let Ok((player_entity, mut player)) = q_player.get_single_mut() else {
+        return;
+    };
+    for event in collision_events.read() {
+        let &CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        let other = if collider1 == player_entity {
+            collider2
+        } else if collider2 == player_entity {
+            collider1
+        } else {
+            continue;
+        };
+        let is_wall = q_groups
+            .get(other)
+            .is_ok_and(|groups| groups.memberships.contains(WALL_GROUP));
+        if is_wall {
+            player.dash = None;
+        }
+    }
+}
+
+/// Flash the player's color and take a life for each hit. Lives are only lost once per
+/// invulnerability period.
+fn handle_player_hits(
+    time: Res<Time>,
+    config: Res<Config>,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut q_player: Query<&mut Player>,
+    mut q_stopwatch: Query<&mut TimeSinceCollision>,
+) {
+    let Ok(mut player) = q_player.get_single_mut() else {
+        return;
+    };
+    player.invulnerable.tick(time.delta());
+
+    // Dashing gives i-frames
+    if hit_events.read().last().is_none() || player.shielded || player.is_dashing() {
+        return;
+    }
+    // Reset timer to trigger color change
+    if let Ok(mut stopwatch) = q_stopwatch.get_single_mut() {
+        stopwatch.reset();
+    }
+    if config.player_lives == 0 || !player.invulnerable.finished() {
+        return;
+    }
+    player.lives = player.lives.saturating_sub(1);
+    player.invulnerable.reset();
+}
+
 /// Transitions color for player-chaser collisions.
 fn transition_color(
     mut query: Query<(&Handle<ColorMaterial>, &mut TimeSinceCollision)>,
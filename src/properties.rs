@@ -0,0 +1,569 @@
+use bevy::prelude::*;
+use std::{fmt, path::PathBuf};
+use tiled::{Map, ObjectData, Properties, PropertyValue};
+
+/// Why a property couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyErrorKind {
+    Missing,
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// Right type but not a usable value, e.g. an unknown enum variant
+    Invalid {
+        value: String,
+        reason: String,
+    },
+    /// A member of a class property couldn't be read
+    Member {
+        key: String,
+        kind: Box<PropertyErrorKind>,
+    },
+}
+
+impl fmt::Display for PropertyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyErrorKind::Missing => write!(f, "is missing"),
+            PropertyErrorKind::WrongType { expected, found } => {
+                write!(f, "should be {expected} but is {found}")
+            }
+            PropertyErrorKind::Invalid { value, reason } => {
+                write!(f, "has invalid value \"{value}\": {reason}")
+            }
+            PropertyErrorKind::Member { key, kind } => write!(f, "member \"{key}\" {kind}"),
+        }
+    }
+}
+
+/// Error reading a custom property. Names the object and key so the property can be found in
+/// Tiled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyError {
+    /// `None` for map and class member properties
+    pub object_id: Option<u32>,
+    pub key: String,
+    pub kind: PropertyErrorKind,
+}
+
+impl fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.object_id {
+            Some(id) => write!(f, "Object ID {id}: ")?,
+            None => write!(f, "Map: ")?,
+        }
+        write!(f, "property \"{}\" {}", self.key, self.kind)
+    }
+}
+
+impl std::error::Error for PropertyError {}
+
+/// Typed access to the custom properties of Tiled objects, maps and class properties
+pub trait CustomProperties {
+    fn property_map(&self) -> &Properties;
+
+    /// Object named in errors
+    fn object_id(&self) -> Option<u32>;
+
+    /// Read a required property
+    fn get_property<T: FromProperty>(&self, key: &str) -> Result<T, PropertyError> {
+        self.get_optional(key)?
+            .ok_or_else(|| self.property_error(key, PropertyErrorKind::Missing))
+    }
+
+    /// Read a property that may be missing. Unset object references are missing too.
+    fn get_optional<T: FromProperty>(&self, key: &str) -> Result<Option<T>, PropertyError> {
+        let Some(value) = self.property_map().get(key) else {
+            return Ok(None);
+        };
+        match T::from_property(value) {
+            Ok(value) => Ok(Some(value)),
+            Err(PropertyErrorKind::Missing) => Ok(None),
+            Err(kind) => Err(self.property_error(key, kind)),
+        }
+    }
+
+    fn property_error(&self, key: &str, kind: PropertyErrorKind) -> PropertyError {
+        PropertyError {
+            object_id: self.object_id(),
+            key: key.to_string(),
+            kind,
+        }
+    }
+}
+
+impl CustomProperties for ObjectData {
+    fn property_map(&self) -> &Properties {
+        &self.properties
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        Some(self.id())
+    }
+}
+
+impl CustomProperties for Map {
+    fn property_map(&self) -> &Properties {
+        &self.properties
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Members of a class property
+impl CustomProperties for Properties {
+    fn property_map(&self) -> &Properties {
+        self
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Types that can be read from a property value
+pub trait FromProperty: Sized {
+    /// Tiled type name used in errors
+    const TYPE_NAME: &'static str;
+
+    /// Returning [`PropertyErrorKind::Missing`] makes optional properties `None`.
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind>;
+}
+
+/// Tiled type name of a property value
+fn type_name(value: &PropertyValue) -> &'static str {
+    match value {
+        PropertyValue::BoolValue(_) => "bool",
+        PropertyValue::FloatValue(_) => "float",
+        PropertyValue::IntValue(_) => "int",
+        PropertyValue::ColorValue(_) => "color",
+        PropertyValue::StringValue(_) => "string",
+        PropertyValue::FileValue(_) => "file",
+        PropertyValue::ObjectValue(_) => "object",
+        PropertyValue::ClassValue { .. } => "class",
+    }
+}
+
+pub fn wrong_type<T: FromProperty>(value: &PropertyValue) -> PropertyErrorKind {
+    PropertyErrorKind::WrongType {
+        expected: T::TYPE_NAME,
+        found: type_name(value),
+    }
+}
+
+impl FromProperty for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match *value {
+            PropertyValue::BoolValue(value) => Ok(value),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+impl FromProperty for i32 {
+    const TYPE_NAME: &'static str = "int";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match *value {
+            PropertyValue::IntValue(value) => Ok(value),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+impl FromProperty for u32 {
+    const TYPE_NAME: &'static str = "int";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match *value {
+            PropertyValue::IntValue(int) => {
+                u32::try_from(int).map_err(|_| PropertyErrorKind::Invalid {
+                    value: int.to_string(),
+                    reason: "must not be negative".to_string(),
+                })
+            }
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+impl FromProperty for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match *value {
+            PropertyValue::FloatValue(value) => Ok(value),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+impl FromProperty for String {
+    const TYPE_NAME: &'static str = "string";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match value {
+            PropertyValue::StringValue(value) => Ok(value.clone()),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+/// File property. Paths are relative to the map file.
+impl FromProperty for PathBuf {
+    const TYPE_NAME: &'static str = "file";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match value {
+            PropertyValue::FileValue(path) => Ok(PathBuf::from(path)),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+impl FromProperty for Color {
+    const TYPE_NAME: &'static str = "color";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match value {
+            PropertyValue::ColorValue(color) => Ok(Color::srgba_u8(
+                color.red,
+                color.green,
+                color.blue,
+                color.alpha,
+            )),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+/// Members of a class property, read with [`CustomProperties`]
+impl FromProperty for Properties {
+    const TYPE_NAME: &'static str = "class";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match value {
+            PropertyValue::ClassValue { properties, .. } => Ok(properties.clone()),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+/// Object id from an object property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectRef(pub u32);
+
+impl FromProperty for ObjectRef {
+    const TYPE_NAME: &'static str = "object";
+
+    /// Tiled uses 0 for an unset object reference, which reads as missing.
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        match *value {
+            PropertyValue::ObjectValue(0) => Err(PropertyErrorKind::Missing),
+            PropertyValue::ObjectValue(id) => Ok(ObjectRef(id)),
+            _ => Err(wrong_type::<Self>(value)),
+        }
+    }
+}
+
+/// Fields of an [`object_properties!`] struct. `Option` fields are `None` when the property is
+/// missing.
+pub trait PropertyField: Sized {
+    fn read(properties: &impl CustomProperties, key: &str) -> Result<Self, PropertyError>;
+}
+
+/// Implement [`PropertyField`] for [`FromProperty`] types and their `Option`
+macro_rules! impl_property_field {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl $crate::properties::PropertyField for $ty {
+                fn read(
+                    properties: &impl $crate::properties::CustomProperties,
+                    key: &str,
+                ) -> Result<Self, $crate::properties::PropertyError> {
+                    $crate::properties::CustomProperties::get_property(properties, key)
+                }
+            }
+
+            impl $crate::properties::PropertyField for Option<$ty> {
+                fn read(
+                    properties: &impl $crate::properties::CustomProperties,
+                    key: &str,
+                ) -> Result<Self, $crate::properties::PropertyError> {
+                    $crate::properties::CustomProperties::get_optional(properties, key)
+                }
+            }
+        )*
+    };
+}
+pub(crate) use impl_property_field;
+
+impl_property_field!(bool, i32, u32, f32, String, PathBuf, Color, Properties, ObjectRef);
+
+/// Declare an enum that is read from a Tiled enum property. Values are variant names, or
+/// variant indices for enums stored as ints.
+macro_rules! property_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+        }
+
+        impl $crate::properties::FromProperty for $name {
+            const TYPE_NAME: &'static str = "enum";
+
+            fn from_property(
+                value: &::tiled::PropertyValue,
+            ) -> Result<Self, $crate::properties::PropertyErrorKind> {
+                let invalid = |value: String| $crate::properties::PropertyErrorKind::Invalid {
+                    value,
+                    reason: format!(
+                        "expected one of {}",
+                        [$(stringify!($variant)),*].join(", ")
+                    ),
+                };
+                match value {
+                    ::tiled::PropertyValue::StringValue(name) => match name.as_str() {
+                        $(stringify!($variant) => Ok($name::$variant),)*
+                        _ => Err(invalid(name.clone())),
+                    },
+                    &::tiled::PropertyValue::IntValue(index) => usize::try_from(index)
+                        .ok()
+                        .and_then(|index| [$($name::$variant),*].into_iter().nth(index))
+                        .ok_or_else(|| invalid(index.to_string())),
+                    _ => Err($crate::properties::wrong_type::<Self>(value)),
+                }
+            }
+        }
+
+        $crate::properties::impl_property_field!($name);
+    };
+}
+pub(crate) use property_enum;
+
+/// Declare the custom properties an object type expects. Generates a struct with a `read`
+/// function that reads every field from the property with the same name.
+///
+/// Fields with a default are optional, as are `Option` fields. The struct can also be used as
+/// the type of a class property.
+///
+/// ```ignore
+/// object_properties! {
+///     struct DoorProperties {
+///         open: bool = false,
+///         key: Option<ObjectRef>,
+///     }
+/// }
+/// ```
+macro_rules! object_properties {
+    (@read $properties:ident, $field:ident, $ty:ty) => {
+        <$ty as $crate::properties::PropertyField>::read($properties, stringify!($field))?
+    };
+    (@read $properties:ident, $field:ident, $ty:ty, $default:expr) => {
+        $crate::properties::CustomProperties::get_optional::<$ty>(
+            $properties,
+            stringify!($field),
+        )?
+        .unwrap_or($default)
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $ty:ty $(= $default:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl $name {
+            pub fn read(
+                properties: &impl $crate::properties::CustomProperties,
+            ) -> Result<Self, $crate::properties::PropertyError> {
+                Ok($name {
+                    $(
+                        $field: $crate::properties::object_properties!(
+                            @read properties, $field, $ty $(, $default)?
+                        ),
+                    )*
+                })
+            }
+        }
+
+        impl $crate::properties::FromProperty for $name {
+            const TYPE_NAME: &'static str = "class";
+
+            fn from_property(
+                value: &::tiled::PropertyValue,
+            ) -> Result<Self, $crate::properties::PropertyErrorKind> {
+                match value {
+                    ::tiled::PropertyValue::ClassValue { properties, .. } => Self::read(properties)
+                        .map_err(|e| $crate::properties::PropertyErrorKind::Member {
+                            key: e.key,
+                            kind: Box::new(e.kind),
+                        }),
+                    _ => Err($crate::properties::wrong_type::<Self>(value)),
+                }
+            }
+        }
+
+        $crate::properties::impl_property_field!($name);
+    };
+}
+pub(crate) use object_properties;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiled::PropertyValue::*;
+
+    property_enum! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum Speed {
+            Slow,
+            Fast,
+        }
+    }
+
+    object_properties! {
+        struct TestProperties {
+            count: u32,
+            speed: Speed = Speed::Slow,
+            target: Option<ObjectRef>,
+        }
+    }
+
+    object_properties! {
+        struct OuterProperties {
+            inner: TestProperties,
+        }
+    }
+
+    fn properties(values: &[(&str, PropertyValue)]) -> Properties {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_all_fields() {
+        let props = properties(&[
+            ("count", IntValue(3)),
+            ("speed", StringValue("Fast".to_string())),
+            ("target", ObjectValue(12)),
+        ]);
+        let read = TestProperties::read(&props).unwrap();
+        assert_eq!(read.count, 3);
+        assert_eq!(read.speed, Speed::Fast);
+        assert_eq!(read.target, Some(ObjectRef(12)));
+    }
+
+    #[test]
+    fn optional_fields_use_defaults() {
+        // An unset object reference is 0
+        let props = properties(&[("count", IntValue(1)), ("target", ObjectValue(0))]);
+        let read = TestProperties::read(&props).unwrap();
+        assert_eq!(read.speed, Speed::Slow);
+        assert_eq!(read.target, None);
+    }
+
+    #[test]
+    fn enum_from_index() {
+        assert_eq!(Speed::from_property(&IntValue(1)), Ok(Speed::Fast));
+        assert!(Speed::from_property(&IntValue(2)).is_err());
+        assert!(Speed::from_property(&IntValue(-1)).is_err());
+    }
+
+    #[test]
+    fn missing_property() {
+        let err = TestProperties::read(&properties(&[])).unwrap_err();
+        assert_eq!(err.key, "count");
+        assert_eq!(err.kind, PropertyErrorKind::Missing);
+        assert_eq!(err.to_string(), "Map: property \"count\" is missing");
+    }
+
+    #[test]
+    fn wrong_property_type() {
+        let props = properties(&[("count", FloatValue(3.0))]);
+        let err = TestProperties::read(&props).unwrap_err();
+        assert_eq!(
+            err.kind,
+            PropertyErrorKind::WrongType {
+                expected: "int",
+                found: "float"
+            }
+        );
+    }
+
+    #[test]
+    fn negative_u32() {
+        let props = properties(&[("count", IntValue(-2))]);
+        let err = TestProperties::read(&props).unwrap_err();
+        assert!(matches!(err.kind, PropertyErrorKind::Invalid { .. }));
+    }
+
+    #[test]
+    fn unknown_enum_variant() {
+        let props = properties(&[
+            ("count", IntValue(1)),
+            ("speed", StringValue("Medium".to_string())),
+        ]);
+        let err = TestProperties::read(&props).unwrap_err();
+        assert_eq!(
+            err.kind,
+            PropertyErrorKind::Invalid {
+                value: "Medium".to_string(),
+                reason: "expected one of Slow, Fast".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_class_property() {
+        let inner = ClassValue {
+            property_type: "TestProperties".to_string(),
+            properties: properties(&[("count", IntValue(4))]),
+        };
+        let read = OuterProperties::read(&properties(&[("inner", inner)])).unwrap();
+        assert_eq!(read.inner.count, 4);
+    }
+
+    #[test]
+    fn class_member_error() {
+        let inner = ClassValue {
+            property_type: "TestProperties".to_string(),
+            properties: properties(&[("count", BoolValue(true))]),
+        };
+        let err = OuterProperties::read(&properties(&[("inner", inner)])).unwrap_err();
+        assert_eq!(err.key, "inner");
+        assert_eq!(
+            err.to_string(),
+            "Map: property \"inner\" member \"count\" should be int but is bool"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    config::{apply_settings, settings_value, Config},
+    player::{read_player_action, PlayerAction, PlayerInputSet},
+    rng::GameRng,
+    tilemap::{load_tilemap, Args},
+};
+
+/// Records player input with `--record <file>` and plays it back with `--replay <file>`
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Startup,
+            (start_recording, start_playback).before(load_tilemap),
+        )
+        // Replace live input once it has been read
+        .add_systems(
+            PreUpdate,
+            play_back_input
+                .run_if(resource_exists::<Playback>)
+                .in_set(PlayerInputSet)
+                .after(read_player_action),
+        )
+        .add_systems(
+            Last,
+            (
+                (record_frame, save_recording)
+                    .chain()
+                    .run_if(resource_exists::<Recording>),
+                advance_playback.run_if(resource_exists::<Playback>),
+            ),
+        );
+    }
+}
+
+/// Everything needed to reproduce a run: the level, the seed, the gameplay config, and the
+/// input of every frame
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    /// Map filename in `assets/levels/`
+    pub map: String,
+    pub seed: u64,
+    /// `Config` fields the run was recorded with, as in the settings file. Replays without it
+    /// play with the defaults.
+    #[serde(default)]
+    pub config: Option<ron::Value>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ReplayFrame {
+    /// Real time since the previous frame. Played back as is so simulation steps line up with
+    /// the recording.
+    pub delta: Duration,
+    pub action: PlayerAction,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Self {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read replay {:?}: {e}", path));
+        ron::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse replay {:?}: {e}", path))
+    }
+
+    /// One frame per line
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::new().depth_limit(2))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+/// Input recorded so far. Written to `path` when the app exits.
+#[derive(Resource, Debug)]
+struct Recording {
+    path: PathBuf,
+    /// Config at the start of the recording
+    config: Option<ron::Value>,
+    frames: Vec<ReplayFrame>,
+}
+
+/// Replay being played back
+#[derive(Resource, Debug)]
+pub struct Playback {
+    replay: Replay,
+    /// Index of the current frame
+    frame: usize,
+}
+
+impl Playback {
+    /// Action for the current frame. Nothing is pressed once the replay has ended.
+    pub fn action(&self) -> PlayerAction {
+        self.replay
+            .frames
+            .get(self.frame)
+            .map_or_else(PlayerAction::default, |frame| frame.action)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+}
+
+fn start_recording(
+    mut commands: Commands,
+    args: Res<Args>,
+    config: Res<Config>,
+    registry: Res<AppTypeRegistry>,
+) {
+    let Some(path) = args.record.clone() else {
+        return;
+    };
+    info!("Recording input to {:?}", path);
+    commands.insert_resource(Recording {
+        path,
+        config: recorded_config(&config, &registry),
+        frames: vec![],
+    });
+}
+
+/// `Config` to store in a replay. Replays without one still play back, with the defaults.
+pub fn recorded_config(config: &Config, registry: &AppTypeRegistry) -> Option<ron::Value> {
+    settings_value(config, &registry.read())
+        .inspect_err(|e| error!("Failed to store config in replay: {e}"))
+        .ok()
+}
+
+/// Load the replay and play its level with its seed and config
+pub fn start_playback(
+    mut commands: Commands,
+    mut args: ResMut<Args>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut config: ResMut<Config>,
+    registry: Res<AppTypeRegistry>,
+) {
+    let Some(path) = args.replay.clone() else {
+        return;
+    };
+    let replay = Replay::load(&path);
+    info!(
+        "Playing back {:?}: {} on seed {}, {} frames",
+        path,
+        replay.map,
+        replay.seed,
+        replay.frames.len()
+    );
+    args.map = Some(replay.map.clone());
+    args.seed = Some(replay.seed);
+    if let Some(value) = replay.config.clone() {
+        if let Err(e) = apply_settings(&mut *config, &registry.read(), value) {
+            error!("Failed to apply replay config: {e}");
+        }
+    }
+    if let Some(frame) = replay.frames.first() {
+        *time_strategy = TimeUpdateStrategy::ManualDuration(frame.delta);
+    }
+    commands.insert_resource(Playback { replay, frame: 0 });
+}
+
+fn play_back_input(playback: Res<Playback>, mut action: ResMut<PlayerAction>) {
+    *action = playback.action();
+}
+
+/// Move to the next frame and give time the duration it had in the recording. Exits after the
+/// last frame.
+pub fn advance_playback(
+    mut playback: ResMut<Playback>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if playback.finished() {
+        return;
+    }
+    playback.frame += 1;
+    match playback.replay.frames.get(playback.frame) {
+        Some(frame) => *time_strategy = TimeUpdateStrategy::ManualDuration(frame.delta),
+        None => {
+            info!("Replay finished");
+            *time_strategy = TimeUpdateStrategy::Automatic;
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+fn record_frame(
+    time: Res<Time<Real>>,
+    action: Res<PlayerAction>,
+    mut recording: ResMut<Recording>,
+) {
+    recording.frames.push(ReplayFrame {
+        delta: time.delta(),
+        action: *action,
+    });
+}
+
+fn save_recording(
+    args: Res<Args>,
+    rng: Res<GameRng>,
+    recording: Res<Recording>,
+    mut exit_events: EventReader<AppExit>,
+) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+    let replay = Replay {
+        map: args.map.clone().unwrap_or_default(),
+        seed: rng.seed(),
+        config: recording.config.clone(),
+        frames: recording.frames.clone(),
+    };
+    match replay.save(&recording.path) {
+        Ok(()) => info!(
+            "Saved {} frames to {:?}",
+            replay.frames.len(),
+            recording.path
+        ),
+        Err(e) => error!("Failed to save replay {:?}: {e}", recording.path),
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::ops::{Deref, DerefMut};
+
+use crate::{physics::SimulationSet, player::ResetEvent};
+
+/// Seeded randomness for gameplay
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameRng::new(rand::random()))
+            .add_systems(FixedUpdate, reset_rng.before(SimulationSet));
+    }
+}
+
+/// All gameplay randomness comes from here, so a run can be reproduced from its seed and
+/// inputs. The seed comes from `--seed`, the `seed` map property, or is picked at random.
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Deref for GameRng {
+    type Target = StdRng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
+
+/// Resetting the level starts the same sequence over. `ResetEvent` is sent during a simulation
+/// step, so this runs at the start of the next one, however many steps the frame has.
+fn reset_rng(mut rng: ResMut<GameRng>, mut reset_events: EventReader<ResetEvent>) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    *rng = GameRng::new(rng.seed);
+}
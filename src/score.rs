@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    config::Config,
+    physics::{SimulationSet, CHASER_GROUP, PLAYER_GROUP},
+    player::{Player, PlayerHitEvent, ResetEvent},
+    wagon::WagonTrain,
+};
+
+const SURVIVAL_POINTS_PER_SEC: f32 = 10.0;
+/// Points for a near-miss before the combo multiplier
+const NEAR_MISS_POINTS: f32 = 50.0;
+/// Multiplier gained for each near-miss in a row
+const COMBO_STEP: f32 = 0.25;
+const MAX_MULTIPLIER: f32 = 5.0;
+/// Seconds the score breakdown stays on screen after a run
+const BREAKDOWN_TIME: f32 = 6.0;
+const SCORE_UI_COLOR: Color = Color::srgb(1.0, 0.9, 0.4);
+
+/// Points for surviving and for near-misses with chasers. Both are multiplied by the number of
+/// wagons the player pulls, and near-misses also by a combo that resets when the player is hit.
+/// Shows a breakdown when the level resets.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (spawn_score_text, spawn_breakdown))
+            .add_systems(
+                FixedUpdate,
+                (count_survival_time, detect_near_misses).in_set(SimulationSet),
+            )
+            .add_systems(Update, (break_combo, update_score_ui, hide_breakdown))
+            // After every `ResetEvent` of the frame was sent, like `reset_rng`
+            .add_systems(PostUpdate, end_run)
+            .add_event::<RunOverEvent>()
+            .init_resource::<Score>();
+    }
+}
+
+/// Score of the current run
+#[derive(Resource, Debug, Clone)]
+pub struct Score {
+    /// Seconds survived
+    pub secs: f32,
+    /// Points from surviving, each second multiplied by the wagons pulled at the time
+    pub survival_points: f32,
+    pub near_misses: u32,
+    /// Points from near-misses, each multiplied by the combo at the time
+    pub near_miss_points: f32,
+    /// Near-misses since the player was last hit
+    pub combo: u32,
+    pub best_multiplier: f32,
+    /// Highest wagon multiplier during the run
+    pub best_wagon_multiplier: u32,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            secs: 0.0,
+            survival_points: 0.0,
+            near_misses: 0,
+            near_miss_points: 0.0,
+            combo: 0,
+            best_multiplier: 1.0,
+            best_wagon_multiplier: 1,
+        }
+    }
+}
+
+impl Score {
+    pub fn multiplier(&self) -> f32 {
+        (1.0 + self.combo as f32 * COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    pub fn total(&self) -> u32 {
+        (self.survival_points + self.near_miss_points) as u32
+    }
+
+    fn add_survival_time(&mut self, secs: f32, wagon_multiplier: u32) {
+        self.secs += secs;
+        self.survival_points += secs * SURVIVAL_POINTS_PER_SEC * wagon_multiplier as f32;
+        self.best_wagon_multiplier = self.best_wagon_multiplier.max(wagon_multiplier);
+    }
+
+    fn add_near_miss(&mut self, wagon_multiplier: u32) {
+        self.near_misses += 1;
+        self.near_miss_points += NEAR_MISS_POINTS * self.multiplier() * wagon_multiplier as f32;
+        self.combo += 1;
+        self.best_multiplier = self.best_multiplier.max(self.multiplier());
+    }
+}
+
+/// Sent with the final score when the level resets
+#[derive(Event, Debug, Clone)]
+pub struct RunOverEvent {
+    pub score: Score,
+}
+
+/// Chaser inside the near-miss radius. Leaving it without touching the player is a near-miss.
+#[derive(Component)]
+struct NearMiss {
+    touched: bool,
+}
+
+/// 1 plus the wagons pulled by the player
+fn wagon_multiplier(q_train: &Query<&WagonTrain, With<Player>>) -> u32 {
+    q_train
+        .get_single()
+        .map_or(1, |train| train.score_multiplier())
+}
+
+fn count_survival_time(
+    time: Res<Time>,
+    mut score: ResMut<Score>,
+    q_train: Query<&WagonTrain, With<Player>>,
+) {
+    score.add_survival_time(time.delta_seconds(), wagon_multiplier(&q_train));
+}
+
+fn detect_near_misses(
+    mut commands: Commands,
+    config: Res<Config>,
+    rapier_context: Res<RapierContext>,
+    mut score: ResMut<Score>,
+    q_player: Query<(Entity, &Transform), With<Player>>,
+    q_train: Query<&WagonTrain, With<Player>>,
+    mut q_near: Query<(Entity, &mut NearMiss)>,
+) {
+    let Ok((player, transform)) = q_player.get_single() else {
+        return;
+    };
+    let mut inside = vec![];
+    let filter = QueryFilter::new().groups(CollisionGroups::new(PLAYER_GROUP, CHASER_GROUP));
+    rapier_context.intersections_with_shape(
+        transform.translation.xy(),
+        0.0,
+        &Collider::ball(config.near_miss_radius),
+        filter,
+        |chaser| {
+            inside.push(chaser);
+            true
+        },
+    );
+    let touching = |chaser| {
+        rapier_context
+            .contact_pair(player, chaser)
+            .is_some_and(|pair| pair.has_any_active_contacts())
+    };
+
+    for (entity, mut near) in q_near.iter_mut() {
+        if inside.contains(&entity) {
+            near.touched |= touching(entity);
+            continue;
+        }
+        commands.entity(entity).remove::<NearMiss>();
+        if !near.touched {
+            score.add_near_miss(wagon_multiplier(&q_train));
+        }
+    }
+    for entity in inside {
+        if !q_near.contains(entity) {
+            // Chasers can be despawned by other simulation systems in the same step
+            commands.entity(entity).try_insert(NearMiss {
+                touched: touching(entity),
+            });
+        }
+    }
+}
+
+fn break_combo(mut score: ResMut<Score>, mut hit_events: EventReader<PlayerHitEvent>) {
+    if hit_events.read().last().is_some() {
+        score.combo = 0;
+    }
+}
+
+/// Draws the score and combo multiplier on screen
+#[derive(Component)]
+struct ScoreUi;
+
+fn spawn_score_text(mut commands: Commands) {
+    commands.spawn((
+        ScoreUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: SCORE_UI_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(170.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_score_ui(
+    score: Res<Score>,
+    q_train: Query<&WagonTrain, With<Player>>,
+    mut text_query: Query<&mut Text, With<ScoreUi>>,
+) {
+    let value = format!(
+        "score {}  x{:.2}  wagons x{}",
+        score.total(),
+        score.multiplier(),
+        wagon_multiplier(&q_train)
+    );
+
+    // Avoid updating text layout every frame
+    let mut text = text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+/// Score breakdown shown at the end of a run
+#[derive(Component)]
+struct Breakdown(Timer);
+
+#[derive(Component)]
+struct BreakdownText;
+
+fn spawn_breakdown(mut commands: Commands) {
+    commands
+        .spawn((
+            Breakdown(Timer::from_seconds(BREAKDOWN_TIME, TimerMode::Once)),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(30.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                BreakdownText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 40.0,
+                        color: SCORE_UI_COLOR,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+            ));
+        });
+}
+
+/// Show the breakdown of the finished run and start a new one
+fn end_run(
+    mut score: ResMut<Score>,
+    mut reset_events: EventReader<ResetEvent>,
+    mut run_over_events: EventWriter<RunOverEvent>,
+    mut q_breakdown: Query<(&mut Breakdown, &mut Visibility)>,
+    mut q_text: Query<&mut Text, With<BreakdownText>>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    let score = std::mem::take(&mut *score);
+    info!("Run over with score {}", score.total());
+
+    let value = format!(
+        "survived {:.1}s  {}\nnear-misses {}  {}\nbest combo x{:.2}\nmost wagons x{}\ntotal {}",
+        score.secs,
+        score.survival_points as u32,
+        score.near_misses,
+        score.near_miss_points as u32,
+        score.best_multiplier,
+        score.best_wagon_multiplier,
+        score.total(),
+    );
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = value;
+    }
+    if let Ok((mut breakdown, mut visibility)) = q_breakdown.get_single_mut() {
+        breakdown.0.reset();
+        *visibility = Visibility::Inherited;
+    }
+    run_over_events.send(RunOverEvent { score });
+}
+
+fn hide_breakdown(time: Res<Time>, mut q_breakdown: Query<(&mut Breakdown, &mut Visibility)>) {
+    for (mut breakdown, mut visibility) in q_breakdown.iter_mut() {
+        if *visibility != Visibility::Hidden && breakdown.0.tick(time.delta()).just_finished() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
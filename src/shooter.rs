@@ -0,0 +1,352 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::PI;
+
+use crate::{
+    chaser::{update_chaser_avoidance, Chaser, CHASER_BORDER_THICKNESS, CHASER_RADIUS},
+    config::Config,
+    flowfield::{apply_force, Flowfield},
+    patrol::Patroller,
+    physics::{InterpolatedTransform, SimulationSet, PLAYER_GROUP, PROJECTILE_GROUP, WALL_GROUP},
+    player::{Player, PlayerHitEvent},
+    tilemap::{TileStorage, Tilemap},
+};
+
+const SHOOTER_FORCE: f32 = 40000.0;
+/// Seconds between shots
+const SHOOTER_FIRE_INTERVAL: f32 = 1.2;
+/// Shooters only fire when the player is closer than this
+const SHOOTER_RANGE: f32 = 400.0;
+const SHOOTER_COLOR: Color = Color::srgb(0.8, 0.1, 0.9);
+
+const PROJECTILE_RADIUS: f32 = 3.0;
+/// Kept low enough that projectiles can't skip over wall colliders in a single physics step
+const PROJECTILE_SPEED: f32 = 300.0;
+/// Seconds before an unblocked projectile is returned to the pool
+const PROJECTILE_LIFETIME: f32 = 4.0;
+const PROJECTILE_COLOR: Color = Color::srgb(1.0, 0.2, 0.6);
+/// Number of projectiles spawned up front
+const PROJECTILE_POOL_SIZE: usize = 256;
+
+/// Ranged enemies that keep their distance from the player
+pub struct ShooterPlugin;
+
+impl Plugin for ShooterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(
+                FixedUpdate,
+                steer_shooters
+                    .after(apply_force)
+                    .before(update_chaser_avoidance)
+                    .in_set(SimulationSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    fire_projectiles,
+                    (handle_projectile_collisions, expire_projectiles).chain(),
+                )
+                    .in_set(SimulationSet),
+            )
+            .register_type::<Shooter>();
+    }
+}
+
+/// Enemy that stays within a flowfield cost band around the player and fires projectiles.
+/// Also has the [`Chaser`] marker so it is counted, reset and avoided like other chasers.
+#[derive(Component, Debug, Reflect)]
+pub struct Shooter {
+    fire_timer: Timer,
+}
+
+impl Default for Shooter {
+    fn default() -> Self {
+        Self {
+            fire_timer: Timer::from_seconds(SHOOTER_FIRE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Projectile {
+    /// Inactive projectiles are waiting in the pool
+    active: bool,
+    lifetime: Timer,
+}
+
+#[derive(Resource)]
+pub struct ShooterAssets {
+    mesh_marker: Mesh2dHandle,
+    material_marker: Handle<ColorMaterial>,
+    mesh_projectile: Mesh2dHandle,
+    material_projectile: Handle<ColorMaterial>,
+}
+
+impl ShooterAssets {
+    /// Ring drawn on top of the chaser svg to tell shooters apart
+    pub fn marker_bundle(&self) -> MaterialMesh2dBundle<ColorMaterial> {
+        MaterialMesh2dBundle {
+            mesh: self.mesh_marker.clone(),
+            material: self.material_marker.clone(),
+            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+            ..default()
+        }
+    }
+}
+
+/// Inactive projectiles. Chaser counts are in the thousands so projectiles are reused rather than
+/// spawned and despawned.
+#[derive(Resource, Default)]
+pub struct ProjectilePool(Vec<Entity>);
+
+impl ProjectilePool {
+    /// Activate a projectile at `position`, spawning a new one if the pool is empty
+    fn fire(
+        &mut self,
+        commands: &mut Commands,
+        assets: &ShooterAssets,
+        position: Vec2,
+        velocity: Vec2,
+    ) {
+        let entity = self
+            .0
+            .pop()
+            .unwrap_or_else(|| spawn_projectile(commands, assets));
+
+        let transform = Transform::from_translation(position.extend(4.0));
+        commands
+            .entity(entity)
+            .insert((
+                Projectile {
+                    active: true,
+                    lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, TimerMode::Once),
+                },
+                transform,
+                // Don't blend in from where the projectile was last fired
+                InterpolatedTransform::new(&transform),
+                Velocity::linear(velocity),
+                Visibility::Visible,
+            ))
+            .remove::<(RigidBodyDisabled, ColliderDisabled)>();
+    }
+
+    /// Deactivate projectile and return it to the pool
+    fn release(&mut self, commands: &mut Commands, entity: Entity, projectile: &mut Projectile) {
+        if !projectile.active {
+            return;
+        }
+        projectile.active = false;
+        commands
+            .entity(entity)
+            .insert((RigidBodyDisabled, ColliderDisabled, Visibility::Hidden));
+        self.0.push(entity);
+    }
+}
+
+/// Spawns an inactive projectile
+fn spawn_projectile(commands: &mut Commands, assets: &ShooterAssets) -> Entity {
+    commands
+        .spawn((
+            Projectile {
+                active: false,
+                lifetime: Timer::default(),
+            },
+            MaterialMesh2dBundle {
+                mesh: assets.mesh_projectile.clone(),
+                material: assets.material_projectile.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RigidBody::KinematicVelocityBased,
+            Velocity::default(),
+            Collider::ball(PROJECTILE_RADIUS),
+            Sensor,
+            CollisionGroups::new(PROJECTILE_GROUP, PLAYER_GROUP | WALL_GROUP),
+            // Walls have no rigid body so they count as static
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+            ActiveEvents::COLLISION_EVENTS,
+            RigidBodyDisabled,
+            ColliderDisabled,
+            Name::new("Projectile"),
+        ))
+        .id()
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let assets = ShooterAssets {
+        mesh_marker: Mesh2dHandle(meshes.add(Annulus::new(
+            CHASER_RADIUS - CHASER_BORDER_THICKNESS,
+            CHASER_RADIUS,
+        ))),
+        material_marker: materials.add(ColorMaterial::from_color(SHOOTER_COLOR)),
+        mesh_projectile: Mesh2dHandle(meshes.add(Circle::new(PROJECTILE_RADIUS))),
+        material_projectile: materials.add(ColorMaterial::from_color(PROJECTILE_COLOR)),
+    };
+
+    let mut pool = ProjectilePool::default();
+    for _ in 0..PROJECTILE_POOL_SIZE {
+        pool.0.push(spawn_projectile(&mut commands, &assets));
+    }
+
+    commands.insert_resource(assets);
+    commands.insert_resource(pool);
+}
+
+/// Move shooters along the flowfield until they are inside the cost band. Patrolling shooters
+/// are steered by their path instead.
+fn steer_shooters(
+    config: Res<Config>,
+    time: Res<Time>,
+    mut q_flowfield: Query<&mut Flowfield>,
+    q_map: Query<&Tilemap>,
+    q_tile_storage: Query<&TileStorage>,
+    q_player: Query<&Transform, (With<Player>, Without<Shooter>)>,
+    mut q_shooters: Query<
+        (&mut ExternalForce, &mut Transform),
+        (With<Shooter>, With<Chaser>, Without<Patroller>),
+    >,
+) {
+    let Ok(map) = q_map.get_single() else {
+        return;
+    };
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let mut flowfield = q_flowfield.single_mut();
+    let storage = q_tile_storage.single();
+    let player_pos = player_transform.translation.xy();
+
+    for (mut force, mut transform) in q_shooters.iter_mut() {
+        let pos = transform.translation.xy();
+        let tile_coords = map.world_to_tile_coords(&pos);
+
+        force.force = match flowfield.get_cost_at_tile(tile_coords) {
+            Some(cost) if cost < config.shooter_cost_min => {
+                // Too close, back away along the flowfield
+                let dir = flowfield.get_flow_at_tile(tile_coords, storage, false);
+                -dir * SHOOTER_FORCE
+            }
+            Some(cost) if cost <= config.shooter_cost_max => {
+                // In range, let damping bring the shooter to a stop
+                Vec2::ZERO
+            }
+            _ => {
+                let dir = flowfield.get_flow_at_tile(tile_coords, storage, config.flowfield_smooth);
+                dir * SHOOTER_FORCE
+            }
+        };
+
+        // Always face the player
+        if let Ok(dir) = Dir2::new(player_pos - pos) {
+            let target = Quat::from_rotation_z(dir.to_angle() - 0.75 * PI);
+            let s = 6.0 * time.delta_seconds();
+            transform.rotation = transform.rotation.lerp(target, s);
+        }
+    }
+}
+
+/// Shooters fire at the player when they have line of sight.
+fn fire_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    assets: Res<ShooterAssets>,
+    mut pool: ResMut<ProjectilePool>,
+    rapier_context: Res<RapierContext>,
+    q_player: Query<&Transform, (With<Player>, Without<Shooter>)>,
+    mut q_shooters: Query<(&mut Shooter, &Transform)>,
+) {
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    // Rays only hit walls
+    let filter = QueryFilter::new().groups(CollisionGroups::new(WALL_GROUP, WALL_GROUP));
+
+    for (mut shooter, transform) in q_shooters.iter_mut() {
+        shooter.fire_timer.tick(time.delta());
+        if !shooter.fire_timer.just_finished() {
+            continue;
+        }
+
+        let pos = transform.translation.xy();
+        let diff = player_pos - pos;
+        if diff.length_squared() > SHOOTER_RANGE * SHOOTER_RANGE {
+            continue;
+        }
+        // With a `max_toi` of 1.0 the ray ends at the player
+        if rapier_context
+            .cast_ray(pos, diff, 1.0, true, filter)
+            .is_some()
+        {
+            continue;
+        }
+        let Ok(dir) = Dir2::new(diff) else {
+            continue;
+        };
+
+        // Spawn outside the shooter's collider
+        let position = pos + dir * (CHASER_RADIUS + PROJECTILE_RADIUS);
+        pool.fire(&mut commands, &assets, position, dir * PROJECTILE_SPEED);
+    }
+}
+
+/// Projectiles are returned to the pool when they hit a wall or the player.
+fn handle_projectile_collisions(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut q_projectiles: Query<&mut Projectile>,
+    q_player: Query<(), With<Player>>,
+    mut hit_events: EventWriter<PlayerHitEvent>,
+) {
+    for event in collision_events.read() {
+        let &CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        let (entity, other) = if q_projectiles.contains(collider1) {
+            (collider1, collider2)
+        } else if q_projectiles.contains(collider2) {
+            (collider2, collider1)
+        } else {
+            continue;
+        };
+        let Ok(mut projectile) = q_projectiles.get_mut(entity) else {
+            continue;
+        };
+        if !projectile.active {
+            continue;
+        }
+
+        if q_player.contains(other) {
+            hit_events.send(PlayerHitEvent);
+        }
+        pool.release(&mut commands, entity, &mut projectile);
+    }
+}
+
+/// Return projectiles that have been flying for too long to the pool.
+fn expire_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<ProjectilePool>,
+    mut q_projectiles: Query<(Entity, &mut Projectile)>,
+) {
+    for (entity, mut projectile) in q_projectiles.iter_mut() {
+        if !projectile.active {
+            continue;
+        }
+        projectile.lifetime.tick(time.delta());
+        if projectile.lifetime.finished() {
+            pool.release(&mut commands, entity, &mut projectile);
+        }
+    }
+}
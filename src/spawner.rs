@@ -1,57 +1,272 @@
-use bevy::prelude::*;
-use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
+use bevy::{color::palettes::css::ORANGE_RED, math::vec2, prelude::*};
+use bevy_rapier2d::prelude::*;
 use bevy_svg::prelude::*;
-use rand::Rng;
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
 use std::{f32::consts::PI, time::Duration};
-use tiled::{ObjectData, PropertyValue};
+use tiled::{ObjectData, ObjectShape};
 
 use crate::{
-    chaser::{Chaser, ChaserAssets, ChaserBundle},
+    chaser::{Chaser, ChaserAssets, ChaserBundle, CHASER_RADIUS},
     config::Config,
+    flowfield::Flowfield,
+    patrol::Patroller,
+    physics::{SimulationSet, CHASER_GROUP, WALL_GROUP},
+    player::{Player, ResetEvent},
+    properties::{object_properties, property_enum, ObjectRef},
+    rng::GameRng,
+    shooter::{Shooter, ShooterAssets},
+    tilemap::{TileStorage, Tilemap},
 };
 
+/// Number of random positions tried per frame before a spawn is put off
+const SPAWN_ATTEMPTS: usize = 8;
+/// Number of samples tried to match a non-uniform distribution or to land inside a polygon
+const SAMPLE_ATTEMPTS: usize = 16;
+/// How far inside a polygon corner the fallback spawn position is
+const POLYGON_INSET: f32 = 1.0;
+/// `density` is the number of enemies per this many square pixels
+const DENSITY_AREA: f32 = 100.0 * 100.0;
+
 pub struct SpawnPlugin;
 
 impl Plugin for SpawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, handle_trigger_event)
-            .add_systems(Update, (update_spawners, remove_chasers).chain())
-            .add_event::<SpawnerTriggerEvent>();
+        app.add_systems(
+            FixedUpdate,
+            (update_spawners, spawn_pending, remove_chasers)
+                .chain()
+                .in_set(SimulationSet),
+        )
+        .add_systems(Update, (draw_pending_spawns, reset_pending_spawns))
+        .add_event::<SpawnerTriggerEvent>();
+    }
+}
+
+property_enum! {
+    /// Type of enemy a [`Spawner`] creates
+    #[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+    pub enum EnemyKind {
+        #[default]
+        Chaser,
+        /// Keeps its distance and fires projectiles
+        Shooter,
     }
 }
 
-/// Implemented on [`tiled::ObjectData`] to get custom property values
-trait CustomProperties {
-    fn get_bool(&self, key: impl AsRef<str>) -> Option<bool>;
-    fn get_i32(&self, key: impl AsRef<str>) -> Option<i32>;
-    fn get_u32(&self, key: impl AsRef<str>) -> Option<u32>;
-    fn get_f32(&self, key: impl AsRef<str>) -> Option<f32>;
+/// Region a [`Spawner`] places enemies in. Offsets are relative to the spawner position, which
+/// is the object position in Tiled.
+#[derive(Default, Reflect, Debug, Clone, PartialEq)]
+pub enum SpawnArea {
+    /// Within `scatter_radius` of the spawner
+    #[default]
+    Point,
+    /// Between `inner_radius` and `scatter_radius` of the spawner
+    Ring {
+        inner_radius: f32,
+    },
+    /// Spanned by two axes from the corner at the spawner position. Rotated rectangles are
+    /// supported since the axes don't have to line up with the world axes.
+    Rect {
+        x_axis: Vec2,
+        y_axis: Vec2,
+    },
+    /// `x_axis` and `y_axis` are half axes
+    Ellipse {
+        center: Vec2,
+        x_axis: Vec2,
+        y_axis: Vec2,
+    },
+    Polygon {
+        points: Vec<Vec2>,
+    },
 }
 
-impl CustomProperties for ObjectData {
-    fn get_bool(&self, key: impl AsRef<str>) -> Option<bool> {
-        if let &PropertyValue::BoolValue(value) = self.properties.get(key.as_ref())? {
-            return Some(value);
+impl SpawnArea {
+    fn from_object(object_data: &ObjectData) -> Self {
+        // Points are relative to the object position and y points down in Tiled
+        let rotation = Rot2::degrees(-object_data.rotation);
+        match object_data.shape {
+            ObjectShape::Point(..) => SpawnArea::Point,
+            // Objects without a shape are empty rectangles
+            ObjectShape::Rect { width, height } if width == 0.0 && height == 0.0 => {
+                SpawnArea::Point
+            }
+            ObjectShape::Rect { width, height } => SpawnArea::Rect {
+                x_axis: rotation * vec2(width, 0.0),
+                y_axis: rotation * vec2(0.0, -height),
+            },
+            ObjectShape::Ellipse { width, height } => SpawnArea::Ellipse {
+                center: rotation * vec2(width * 0.5, -height * 0.5),
+                x_axis: rotation * vec2(width * 0.5, 0.0),
+                y_axis: rotation * vec2(0.0, height * 0.5),
+            },
+            ObjectShape::Polygon { ref points } if points.len() >= 3 => SpawnArea::Polygon {
+                points: points
+                    .iter()
+                    .map(|&(x, y)| rotation * vec2(x, -y))
+                    .collect(),
+            },
+            _ => panic!(
+                "Spawner shape not supported. Object ID: {}",
+                object_data.id()
+            ),
         }
-        None
     }
-    fn get_i32(&self, key: impl AsRef<str>) -> Option<i32> {
-        if let &PropertyValue::IntValue(value) = self.properties.get(key.as_ref())? {
-            return Some(value);
+
+    /// Size in square pixels
+    fn area(&self, scatter_radius: f32) -> f32 {
+        match self {
+            SpawnArea::Point => PI * scatter_radius * scatter_radius,
+            SpawnArea::Ring { inner_radius } => {
+                PI * (scatter_radius * scatter_radius - inner_radius * inner_radius).max(0.0)
+            }
+            SpawnArea::Rect { x_axis, y_axis } => x_axis.perp_dot(*y_axis).abs(),
+            SpawnArea::Ellipse { x_axis, y_axis, .. } => PI * x_axis.length() * y_axis.length(),
+            SpawnArea::Polygon { points } => {
+                // Shoelace formula
+                let next = points.iter().cycle().skip(1);
+                let sum = points
+                    .iter()
+                    .zip(next)
+                    .map(|(a, b)| a.perp_dot(*b))
+                    .sum::<f32>();
+                sum.abs() * 0.5
+            }
         }
-        None
     }
-    fn get_u32(&self, key: impl AsRef<str>) -> Option<u32> {
-        if let &PropertyValue::ObjectValue(value) = self.properties.get(key.as_ref())? {
-            return Some(value);
+
+    pub fn center(&self) -> Vec2 {
+        match self {
+            SpawnArea::Point | SpawnArea::Ring { .. } => Vec2::ZERO,
+            SpawnArea::Rect { x_axis, y_axis } => (*x_axis + *y_axis) * 0.5,
+            SpawnArea::Ellipse { center, .. } => *center,
+            SpawnArea::Polygon { points } => {
+                points.iter().copied().sum::<Vec2>() / points.len() as f32
+            }
         }
-        None
     }
-    fn get_f32(&self, key: impl AsRef<str>) -> Option<f32> {
-        if let &PropertyValue::FloatValue(value) = self.properties.get(key.as_ref())? {
-            return Some(value);
+
+    /// Distance from the center to the farthest point of the area
+    fn max_dist(&self, scatter_radius: f32) -> f32 {
+        let center = self.center();
+        match self {
+            SpawnArea::Point | SpawnArea::Ring { .. } => scatter_radius,
+            SpawnArea::Rect { x_axis, y_axis } => {
+                let diagonal = (*x_axis + *y_axis)
+                    .length()
+                    .max((*x_axis - *y_axis).length());
+                diagonal * 0.5
+            }
+            SpawnArea::Ellipse { x_axis, y_axis, .. } => x_axis.length().max(y_axis.length()),
+            SpawnArea::Polygon { points } => points
+                .iter()
+                .map(|p| p.distance(center))
+                .fold(0.0, f32::max),
         }
-        None
+    }
+
+    /// Uniformly distributed offset inside the area
+    fn sample(&self, rng: &mut StdRng, scatter_radius: f32) -> Vec2 {
+        match self {
+            SpawnArea::Point => unit_disc(rng) * scatter_radius,
+            SpawnArea::Ring { inner_radius } => {
+                // Uniform over the area, so the squared radius is uniform
+                let inner = inner_radius * inner_radius;
+                let outer = (scatter_radius * scatter_radius).max(inner);
+                let r = (inner + (outer - inner) * rng.gen::<f32>()).sqrt();
+                Vec2::from_angle(rng.gen_range(-PI..PI)) * r
+            }
+            SpawnArea::Rect { x_axis, y_axis } => {
+                *x_axis * rng.gen::<f32>() + *y_axis * rng.gen::<f32>()
+            }
+            SpawnArea::Ellipse {
+                center,
+                x_axis,
+                y_axis,
+            } => {
+                let p = unit_disc(rng);
+                *center + *x_axis * p.x + *y_axis * p.y
+            }
+            SpawnArea::Polygon { points } => {
+                // Rejection sampling within the bounding box
+                let min = points.iter().copied().fold(Vec2::MAX, Vec2::min);
+                let max = points.iter().copied().fold(Vec2::MIN, Vec2::max);
+                (0..SAMPLE_ATTEMPTS)
+                    .map(|_| min + (max - min) * vec2(rng.gen(), rng.gen()))
+                    .find(|&p| polygon_contains(points, p))
+                    .or_else(|| polygon_interior_point(points))
+                    .unwrap_or_else(|| self.center())
+            }
+        }
+    }
+}
+
+/// A point just inside a corner of the polygon. The centroid can be outside concave polygons.
+fn polygon_interior_point(points: &[Vec2]) -> Option<Vec2> {
+    let prev = points.iter().cycle().skip(points.len() - 1);
+    let next = points.iter().cycle().skip(1);
+    points
+        .iter()
+        .zip(prev)
+        .zip(next)
+        .find_map(|((&b, &a), &c)| {
+            // Step along the corner's bisector, inwards for convex corners
+            let bisector =
+                ((a - b).normalize_or_zero() + (c - b).normalize_or_zero()).normalize_or_zero();
+            [bisector, -bisector]
+                .into_iter()
+                .map(|dir| b + dir * POLYGON_INSET)
+                .find(|&p| polygon_contains(points, p))
+        })
+}
+
+/// Uniform over the unit disc
+fn unit_disc(rng: &mut StdRng) -> Vec2 {
+    let r = rng.gen::<f32>().sqrt();
+    Vec2::from_angle(rng.gen_range(-PI..PI)) * r
+}
+
+/// Even-odd rule
+fn polygon_contains(points: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    let prev = points.iter().cycle().skip(points.len() - 1);
+    for (&a, &b) in points.iter().zip(prev) {
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+property_enum! {
+    /// How enemies are spread over a [`SpawnArea`]
+    #[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SpawnDistribution {
+        #[default]
+        Uniform,
+        /// More likely near the center
+        Center,
+        /// More likely near the border
+        Edge,
+    }
+}
+
+object_properties! {
+    /// Custom properties of a [`Spawner`] object
+    struct SpawnerProperties {
+        active: bool,
+        num_spawn: i32,
+        delay: f32,
+        immediate: bool,
+        interval: f32,
+        repeats: bool,
+        kind: EnemyKind = EnemyKind::Chaser,
+        path_id: Option<ObjectRef>,
+        scatter_radius: f32 = 0.0,
+        distribution: SpawnDistribution = SpawnDistribution::Uniform,
+        /// Overrides `num_spawn` so larger areas spawn more enemies
+        density: Option<f32>,
     }
 }
 
@@ -66,18 +281,45 @@ pub struct Spawner {
     pub interval: f32,
     pub repeats: bool,
     pub count: i32,
+    /// Enemies placed since the level started
+    pub spawned: i32,
     pub timer: Timer,
+    /// Spawned chasers follow this [`PatrolPath`](crate::patrol::PatrolPath) if set
+    pub path_id: Option<u32>,
+    pub kind: EnemyKind,
+    /// Controlled by the [`WaveDirector`](crate::director::WaveDirector). Triggers don't
+    /// activate it directly.
+    pub directed: bool,
+    /// Enemies spawn at a random position within this distance. Only used by point and ring
+    /// spawners.
+    pub scatter_radius: f32,
+    pub area: SpawnArea,
+    pub distribution: SpawnDistribution,
+    /// No safe position was found. Placement is retried every frame until one is.
+    pub blocked: bool,
 }
 
 impl Spawner {
     pub fn from_object(object_data: &ObjectData) -> Self {
-        // Get custom properties
-        let active_default = object_data.get_bool("active").unwrap();
-        let num_spawn = object_data.get_i32("num_spawn").unwrap();
-        let delay = object_data.get_f32("delay").unwrap();
-        let immediate = object_data.get_bool("immediate").unwrap();
-        let interval = object_data.get_f32("interval").unwrap();
-        let repeats = object_data.get_bool("repeats").unwrap();
+        let properties = SpawnerProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        let SpawnerProperties {
+            active: active_default,
+            mut num_spawn,
+            delay,
+            immediate,
+            interval,
+            repeats,
+            kind,
+            path_id,
+            scatter_radius,
+            distribution,
+            density,
+        } = properties;
+        let area = SpawnArea::from_object(object_data);
+        if let Some(density) = density {
+            let count = area.area(scatter_radius) * density / DENSITY_AREA;
+            num_spawn = (count.round() as i32).max(1);
+        }
 
         // Setup timer
         let duration = if immediate { 0.0 } else { delay };
@@ -93,148 +335,371 @@ impl Spawner {
             interval,
             repeats,
             count: 0,
+            spawned: 0,
             timer,
+            path_id: path_id.map(|path| path.0),
+            kind,
+            directed: false,
+            scatter_radius,
+            area,
+            distribution,
+            blocked: false,
         }
     }
 }
 
-#[derive(Component, Default, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
-pub struct SpawnerTrigger {
-    /// object id of this trigger (only used for debug view atm)
-    id: u32,
-    /// Which spawner this trigger will activate
-    pub spawner_id: u32,
-}
+impl Spawner {
+    /// Restore the state the spawner was loaded with
+    pub fn reset(&mut self) {
+        self.timer = if self.immediate {
+            Timer::new(Duration::ZERO, TimerMode::Repeating)
+        } else {
+            Timer::from_seconds(self.delay, TimerMode::Repeating)
+        };
+        self.count = 0;
+        self.spawned = 0;
+        self.active = self.active_default;
+        self.blocked = false;
+    }
 
-impl SpawnerTrigger {
-    #[inline(always)]
-    pub fn from_object(object_data: &ObjectData) -> Self {
-        let spawner_id = object_data.get_u32("spawner_id").unwrap();
-        SpawnerTrigger {
-            id: object_data.id(),
-            spawner_id,
+    /// Random offset from the spawner position inside its area
+    fn sample_offset(&self, rng: &mut StdRng) -> Vec2 {
+        let center = self.area.center();
+        let max_dist = self.area.max_dist(self.scatter_radius);
+
+        // Keep candidates with a probability based on distance from the center
+        for _ in 0..SAMPLE_ATTEMPTS {
+            let offset = self.area.sample(rng, self.scatter_radius);
+            let dist = if max_dist > 0.0 {
+                offset.distance(center) / max_dist
+            } else {
+                0.0
+            };
+            let weight = match self.distribution {
+                SpawnDistribution::Uniform => 1.0,
+                SpawnDistribution::Center => 1.0 - dist,
+                SpawnDistribution::Edge => dist,
+            };
+            if rng.gen::<f32>() < weight {
+                return offset;
+            }
+        }
+        self.area.sample(rng, self.scatter_radius)
+    }
+
+    /// Apply a trigger action. Finished spawners can only be activated again after a reset.
+    pub fn apply(&mut self, action: SpawnerAction) {
+        match action {
+            SpawnerAction::Activate => {
+                if self.count < self.num_spawn {
+                    self.active = true;
+                }
+            }
+            SpawnerAction::Deactivate => self.active = false,
+            SpawnerAction::Toggle => {
+                if self.active {
+                    self.active = false;
+                } else {
+                    self.apply(SpawnerAction::Activate);
+                }
+            }
+            SpawnerAction::Reset => self.reset(),
         }
     }
+}
 
-    #[inline(always)]
-    pub fn id(&self) -> u32 {
-        self.id
+property_enum! {
+    /// What a trigger action does to a spawner
+    #[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SpawnerAction {
+        #[default]
+        Activate,
+        Deactivate,
+        Toggle,
+        /// Restore the spawner to its initial state
+        Reset,
     }
 }
 
-/// Removes chasers when max_chasers is reduced
+/// Removes chasers when max_chasers is reduced. Pending spawns count as chasers and are removed
+/// first.
 fn remove_chasers(
     mut commands: Commands,
     config: Res<Config>,
+    q_pending: Query<Entity, With<PendingSpawn>>,
     chaser_query: Query<Entity, With<Chaser>>,
 ) {
-    let delete_count = chaser_query.iter().count() as isize - config.max_chasers as isize;
-    for (i, entity) in chaser_query.iter().enumerate() {
-        if i as isize >= delete_count {
-            return;
-        }
+    let count = q_pending.iter().count() + chaser_query.iter().count();
+    let delete_count = count.saturating_sub(config.max_chasers);
+    for entity in q_pending
+        .iter()
+        .chain(chaser_query.iter())
+        .take(delete_count)
+    {
         commands.entity(entity).despawn_recursive();
     }
 }
 
-#[derive(Event)]
-pub struct SpawnerTriggerEvent(pub SpawnerTrigger);
+#[derive(Event, Debug, Clone)]
+pub struct SpawnerTriggerEvent {
+    /// Object id of the trigger or timer that ran the action
+    pub source: u32,
+    pub action: SpawnerAction,
+    pub spawner_ids: Vec<u32>,
+}
+
+/// Enemy waiting to appear behind a warning marker
+#[derive(Component, Debug)]
+pub struct PendingSpawn {
+    timer: Timer,
+    kind: EnemyKind,
+    path_id: Option<u32>,
+}
+
+/// Checks candidate spawn positions against the spawn rules in [`Config`]
+struct Placement<'a> {
+    config: &'a Config,
+    rapier_context: &'a RapierContext,
+    flowfield: Option<&'a Flowfield>,
+    map: Option<(&'a Tilemap, &'a TileStorage)>,
+    player_pos: Option<Vec2>,
+}
+
+impl Placement<'_> {
+    /// Try random positions in the spawner's area and return the first safe one
+    fn find(&self, rng: &mut StdRng, spawner: &Spawner, origin: Vec2) -> Option<Vec2> {
+        (0..SPAWN_ATTEMPTS)
+            .map(|_| origin + spawner.sample_offset(rng))
+            .find(|&pos| self.is_safe(pos))
+    }
+
+    fn is_safe(&self, pos: Vec2) -> bool {
+        if let Some(player_pos) = self.player_pos {
+            if pos.distance(player_pos) < self.config.spawn_min_player_dist {
+                return false;
+            }
+        }
+        if let Some((map, storage)) = self.map {
+            if storage.is_wall(map, pos) {
+                return false;
+            }
+            // Cost is u32::MAX for tiles the flowfield hasn't reached
+            let tile = map.world_to_tile_coords(&pos);
+            let cost = self.flowfield.and_then(|f| f.get_cost_at_tile(tile));
+            if cost.is_some_and(|cost| cost < self.config.spawn_min_player_cost) {
+                return false;
+            }
+        }
+        // Don't stack on top of other enemies
+        let filter = QueryFilter::new().groups(CollisionGroups::new(
+            CHASER_GROUP,
+            CHASER_GROUP | WALL_GROUP,
+        ));
+        let shape = Collider::ball(CHASER_RADIUS);
+        self.rapier_context
+            .intersection_with_shape(pos, 0.0, &shape, filter)
+            .is_none()
+    }
+}
 
 // TODO spawn chasers as a child of some entity to not clog world inspector ui
 /// Spawns chasers
-fn update_spawners(
+pub fn update_spawners(
     mut commands: Commands,
     config: Res<Config>,
     chaser_assets: Res<ChaserAssets>,
+    shooter_assets: Res<ShooterAssets>,
     time: Res<Time>,
+    mut game_rng: ResMut<GameRng>,
+    rapier_context: Res<RapierContext>,
     mut spawner_events: EventReader<SpawnerTriggerEvent>,
     chasers: Query<&Chaser>,
+    q_pending: Query<(), With<PendingSpawn>>,
+    q_flowfield: Query<&Flowfield>,
+    q_map: Query<(&Tilemap, &TileStorage)>,
+    q_player: Query<&Transform, With<Player>>,
     mut q_spawners: Query<(&mut Spawner, &GlobalTransform)>,
 ) {
     // TODO store chaser count somewhere so it don't need to be recalculated per frame
-    if chasers.iter().count() >= config.max_chasers {
-        return;
-    }
+    // Pending spawns will become chasers, so they count towards the limit
+    let count = chasers.iter().count() + q_pending.iter().count();
+    let mut room = config.max_chasers.saturating_sub(count);
 
-    // Get list of spawners which were just triggered
-    let spawner_ids = spawner_events
-        .read()
-        .map(|e| e.0.spawner_id)
-        .collect::<Vec<_>>();
+    // Triggers that just fired
+    let events = spawner_events.read().collect::<Vec<_>>();
 
-    let mut rng = rand::thread_rng();
+    let rng: &mut StdRng = &mut game_rng;
+    let placement = Placement {
+        config: &config,
+        rapier_context: &rapier_context,
+        flowfield: q_flowfield.get_single().ok(),
+        map: q_map.get_single().ok(),
+        player_pos: q_player.get_single().ok().map(|t| t.translation.xy()),
+    };
 
     for (mut spawner, transform) in q_spawners.iter_mut() {
-        // Activate spawner
-        if !spawner.active && spawner.count < spawner.num_spawn && spawner_ids.contains(&spawner.id)
-        {
-            spawner.active = true;
+        // Apply trigger actions in the order they fired
+        if !spawner.directed {
+            for event in events
+                .iter()
+                .filter(|e| e.spawner_ids.contains(&spawner.id))
+            {
+                spawner.apply(event.action);
+            }
         }
-        // If spawner is active, spawn chaser if timer just finished
-        if spawner.active {
+        // If spawner is active, spawn chaser if timer just finished. Triggers still apply at
+        // the chaser limit.
+        if !spawner.active || room == 0 {
+            continue;
+        }
+        // A blocked spawner keeps its timer paused until it finds a safe position
+        if !spawner.blocked {
             spawner.timer.tick(time.delta());
+        }
+        if !spawner.blocked && !spawner.timer.just_finished() {
+            continue;
+        }
+        let center = transform.translation();
+        let Some(position) = placement.find(rng, &spawner, center.xy()) else {
+            spawner.blocked = true;
+            continue;
+        };
+        // Keep the spawner's z so enemies are drawn above the tilemap
+        let translation = position.extend(center.z);
+        spawner.blocked = false;
+        room -= 1;
 
-            // TODO comments?
-            if spawner.timer.just_finished() {
-                spawner.count += 1;
-                if spawner.count == spawner.num_spawn {
-                    if spawner.repeats {
-                        spawner.count = 0;
-                        let duration = (spawner.delay - spawner.timer.elapsed_secs()).max(0.0);
-                        spawner
-                            .timer
-                            .set_duration(Duration::from_secs_f32(duration));
-                    } else {
-                        spawner.active = false;
-                    }
-                } else {
-                    let duration = spawner.interval - spawner.timer.elapsed_secs();
-                    spawner
-                        .timer
-                        .set_duration(Duration::from_secs_f32(duration));
-                }
-
-                let angle = rng.gen_range(-PI..PI);
-                let rotation = Quat::from_rotation_z(angle);
-
-                commands.spawn((
-                    ChaserBundle::default(),
-                    Svg2dBundle {
-                        svg: chaser_assets.svg.clone(),
-                        origin: Origin::TopLeft,
-                        transform: Transform {
-                            translation: transform.translation(),
-                            scale: Vec3::ONE,
-                            rotation,
-                        },
-                        ..default()
-                    },
-                    Chaser,
-                    Name::new("Chaser"),
-                ));
+        spawner.count += 1;
+        spawner.spawned += 1;
+        if spawner.count == spawner.num_spawn {
+            if spawner.repeats {
+                spawner.count = 0;
+                let duration = (spawner.delay - spawner.timer.elapsed_secs()).max(0.0);
+                spawner
+                    .timer
+                    .set_duration(Duration::from_secs_f32(duration));
+            } else {
+                spawner.active = false;
             }
+        } else {
+            let duration = (spawner.interval - spawner.timer.elapsed_secs()).max(0.0);
+            spawner
+                .timer
+                .set_duration(Duration::from_secs_f32(duration));
+        }
+
+        if config.spawn_telegraph_time > 0.0 {
+            commands.spawn((
+                PendingSpawn {
+                    timer: Timer::from_seconds(config.spawn_telegraph_time, TimerMode::Once),
+                    kind: spawner.kind,
+                    path_id: spawner.path_id,
+                },
+                TransformBundle::from_transform(Transform::from_translation(translation)),
+                Name::new("PendingSpawn"),
+            ));
+        } else {
+            spawn_enemy(
+                &mut commands,
+                &chaser_assets,
+                &shooter_assets,
+                rng,
+                translation,
+                spawner.kind,
+                spawner.path_id,
+            );
         }
     }
 }
 
-fn handle_trigger_event(
-    mut spawner_events: EventWriter<SpawnerTriggerEvent>,
-    mut collision_events: EventReader<CollisionEvent>,
-    q_trigger: Query<&SpawnerTrigger, With<Sensor>>,
+/// Spawns an enemy with a random rotation
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    chaser_assets: &ChaserAssets,
+    shooter_assets: &ShooterAssets,
+    rng: &mut StdRng,
+    translation: Vec3,
+    kind: EnemyKind,
+    path_id: Option<u32>,
 ) {
-    for event in collision_events.read() {
-        // Only check SENSOR colliders (trigger has sensor)
-        let &CollisionEvent::Started(collider1, collider2, CollisionEventFlags::SENSOR) = event
-        else {
-            continue;
-        };
-        // If collider is not a trigger, skip
-        let Ok(&trigger) = q_trigger
-            .get(collider1)
-            .or_else(|_| q_trigger.get(collider2))
-        else {
+    let angle = rng.gen_range(-PI..PI);
+    let rotation = Quat::from_rotation_z(angle);
+
+    let mut chaser = commands.spawn((
+        ChaserBundle::default(),
+        Svg2dBundle {
+            svg: chaser_assets.svg.clone(),
+            origin: Origin::TopLeft,
+            transform: Transform {
+                translation,
+                scale: Vec3::ONE,
+                rotation,
+            },
+            ..default()
+        },
+        Chaser,
+        Name::new("Chaser"),
+    ));
+    if let Some(path_id) = path_id {
+        chaser.insert(Patroller::new(path_id));
+    }
+    if kind == EnemyKind::Shooter {
+        chaser
+            .insert((Shooter::default(), Name::new("Shooter")))
+            .with_children(|parent| {
+                parent.spawn(shooter_assets.marker_bundle());
+            });
+    }
+}
+
+/// Replace pending spawns with enemies once their warning is over
+fn spawn_pending(
+    mut commands: Commands,
+    time: Res<Time>,
+    chaser_assets: Res<ChaserAssets>,
+    shooter_assets: Res<ShooterAssets>,
+    mut game_rng: ResMut<GameRng>,
+    mut q_pending: Query<(Entity, &mut PendingSpawn, &Transform)>,
+) {
+    let rng: &mut StdRng = &mut game_rng;
+
+    for (entity, mut pending, transform) in q_pending.iter_mut() {
+        pending.timer.tick(time.delta());
+        if !pending.timer.finished() {
             continue;
-        };
-        spawner_events.send(SpawnerTriggerEvent(trigger));
+        }
+        commands.entity(entity).despawn_recursive();
+        spawn_enemy(
+            &mut commands,
+            &chaser_assets,
+            &shooter_assets,
+            rng,
+            transform.translation,
+            pending.kind,
+            pending.path_id,
+        );
+    }
+}
+
+/// Warning marker that closes in on the spawn position
+fn draw_pending_spawns(mut gizmos: Gizmos, q_pending: Query<(&PendingSpawn, &Transform)>) {
+    for (pending, transform) in q_pending.iter() {
+        let pos = transform.translation.xy();
+        let remaining = 1.0 - pending.timer.fraction();
+        gizmos.circle_2d(pos, CHASER_RADIUS, ORANGE_RED);
+        gizmos.circle_2d(pos, CHASER_RADIUS * (1.0 + 2.0 * remaining), ORANGE_RED);
+    }
+}
+
+fn reset_pending_spawns(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    q_pending: Query<Entity, With<PendingSpawn>>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for entity in q_pending.iter() {
+        commands.entity(entity).despawn_recursive();
     }
 }
@@ -8,15 +8,24 @@ use bevy_fast_tilemap::prelude::*;
 use bevy_rapier2d::prelude::*;
 use clap::Parser;
 use std::{f32::consts::TAU, path::PathBuf};
-use tiled::{Loader, TileId, TileLayer};
+use tiled::{Loader, ObjectData, TileId, TileLayer};
 
 use crate::{
+    boss::Boss,
     cursor::MyWorldCoords,
+    director::{WaveDirector, WaveScript},
+    door::Door,
     flowfield::*,
+    patrol::PatrolPath,
     physics::{PLAYER_GROUP, TRIGGER_GROUP, WALL_GROUP},
+    pickup::Pickup,
     point::Point,
+    properties::CustomProperties,
+    rng::GameRng,
     segment::Segment,
-    spawner::{Spawner, SpawnerTrigger},
+    spawner::Spawner,
+    trigger::{LevelText, LevelTimer, TriggerZone},
+    wagon::Wagon,
 };
 
 const EMPTY_TILE_ID: TileId = 5;
@@ -26,13 +35,22 @@ const OBJECT_LAYER: &str = "object layer";
 const PLAYER_SPAWN: &str = "PlayerSpawn";
 const ENEMY_SPAWNER: &str = "Spawner";
 const SPAWNER_TRIGGER: &str = "SpawnerTrigger";
+const TRIGGER: &str = "Trigger";
+const DOOR: &str = "Door";
+const LEVEL_TEXT: &str = "Text";
+const LEVEL_TIMER: &str = "Timer";
+/// Point with no behavior. Used as a camera target.
+const MARKER: &str = "Marker";
+const PATROL_PATH: &str = "PatrolPath";
+const BOSS: &str = "Boss";
+const PICKUP: &str = "Pickup";
+const WAGON: &str = "Wagon";
 
 pub struct MyTilemapPlugin;
 
 impl Plugin for MyTilemapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (load_tilemap, print_vertex_count).chain())
-            .init_resource::<Args>();
+        app.add_systems(Startup, (load_tilemap, print_vertex_count).chain());
         // .add_systems(Update, print_tile_coords)
         // .add_systems(Update, get_tile_at_cursor)
     }
@@ -65,6 +83,13 @@ impl Tilemap {
         )
     }
 
+    /// True if `world_coords` is on the map. [`Self::world_to_tile_coords`] clamps positions
+    /// outside it to the nearest edge tile.
+    pub fn contains(&self, world_coords: Vec2) -> bool {
+        (0.0..self.get_physical_width()).contains(&world_coords.x)
+            && (0.0..self.get_physical_height()).contains(&world_coords.y)
+    }
+
     pub fn world_to_tile_coords(&self, world_coords: &Vec2) -> Vec2 {
         vec2(
             (world_coords.x / self.tile_width).clamp(0.0, self.width as f32 - 1.),
@@ -89,41 +114,148 @@ pub struct MyTileCoords(pub Vec2);
 #[derive(Parser, Debug, Resource, Default)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// map filename
-    #[arg(short, long)]
-    pub map: String,
-    /// perform benchmark
-    #[arg(short, long)]
-    pub bench: bool,
+    /// map filename. Taken from the replay or scenario file with `--replay` or `--bench`.
+    #[arg(short, long, required_unless_present_any = ["replay", "bench"])]
+    pub map: Option<String>,
+    /// run a benchmark scenario file and write the results
+    #[arg(short, long, conflicts_with_all = ["record", "replay"])]
+    pub bench: Option<PathBuf>,
+    /// benchmark results file. CSV files get a row appended, anything else is written as JSON.
+    #[arg(long, requires = "bench")]
+    pub bench_out: Option<PathBuf>,
+    /// seed for gameplay randomness. Overrides the map's `seed` property.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// record input to this replay file
+    #[arg(long, conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// play back a replay file instead of live input
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    /// run without a window or renderer
+    #[arg(long)]
+    pub headless: bool,
+    /// exit after simulating this many frames in headless mode
+    #[arg(long, requires = "headless")]
+    pub ticks: Option<u32>,
+    /// settings file to load and save `Config` and `DebugViews` with, instead of the one in the
+    /// config directory
+    #[arg(long)]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Component, Default, Reflect, Debug)]
 pub struct PlayerSpawn;
 
+/// Tiled object id. Added to every entity spawned from the object layer so actions can
+/// reference it.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectId(pub u32);
+
 #[derive(Component, Default)]
 pub struct TileStorage(pub Vec<u8>);
 
+impl TileStorage {
+    /// True if the tile at `world_coords` has any wall in it. Everything outside the map is wall.
+    pub fn is_wall(&self, map: &Tilemap, world_coords: Vec2) -> bool {
+        if !map.contains(world_coords) {
+            return true;
+        }
+        let tile = map.world_to_tile_coords(&world_coords);
+        let index = tile.x as usize + tile.y as usize * map.width;
+        self.0.get(index).is_some_and(|&mask| mask != 0)
+    }
+}
+
 /// Marker
 #[derive(Component)]
 pub struct WallCollider;
 
+/// Collider for a rect or ellipse object. Adjusts `transform` for the object's rotation and
+/// anchor.
+fn shape_collider(object_data: &ObjectData, transform: &mut Transform) -> Collider {
+    let angle = f32::to_radians(-object_data.rotation);
+    let rotation = Quat::from_rotation_z(angle);
+    transform.rotate(rotation);
+
+    // This is required because in Tiled, shapes are anchored at their top
+    // left corner while in Bevy they are anchored at their center.
+    let update_transform = |translation: &mut Vec3, half_x: f32, half_y: f32| {
+        // north west corner
+        let nw_corner = vec2(-half_x, half_y);
+        // north west corner after rotation
+        let new_nw_corner = Rot2::radians(angle) * nw_corner;
+
+        let offset_rotation = nw_corner - new_nw_corner;
+        let offset = vec3(half_x, -half_y, 0.0);
+
+        *translation += offset_rotation.extend(0.0) + offset;
+    };
+
+    match object_data.shape {
+        tiled::ObjectShape::Rect { width, height } => {
+            let half_x = width * 0.5;
+            let half_y = height * 0.5;
+
+            update_transform(&mut transform.translation, half_x, half_y);
+            Collider::cuboid(half_x, half_y)
+        }
+        tiled::ObjectShape::Ellipse { width, height } => {
+            let half_x = width * 0.5;
+            let half_y = height * 0.5;
+
+            update_transform(&mut transform.translation, half_x, half_y);
+
+            // There is no ellipse collider but we can create a ball and
+            // then set the scale which will convert it into a elliptical
+            // polygon if necessary.
+            let mut ellipse = Collider::ball(half_x);
+
+            let ratio = height / width;
+            println!("height: {height}, width: {width}, ratio: {ratio}");
+            if ratio != 1.0 {
+                // circle -> ellipse
+                transform.scale = vec3(1.0, ratio, 1.0);
+                ellipse.set_scale(vec2(1.0, ratio), 20);
+            }
+            ellipse
+        }
+        _ => panic!("Shape not supported. Object ID: {}", object_data.id()),
+    }
+}
+
 // TODO refactor!
-fn load_tilemap(
+pub fn load_tilemap(
     mut commands: Commands,
     mut materials: ResMut<Assets<Map>>,
-    mut args: ResMut<Args>,
+    args: Res<Args>,
+    mut rng: ResMut<GameRng>,
     asset_server: Res<AssetServer>,
 ) {
     println!("Loading tilemap...");
     const FRONT_LAYER_Z: f32 = -20.0;
 
-    *args = Args::parse();
-
+    let map_name = args.map.as_deref().expect("No map given");
     let map_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
-        .join(format!("assets/levels/{}", args.map));
+        .join(format!("assets/levels/{}", map_name));
 
     let mut loader = Loader::new();
     let map = loader.load_tmx_map(map_path).unwrap();
+
+    // Seed from the command line, then the map. Otherwise keep the random one.
+    let map_seed = map
+        .get_optional::<u32>("seed")
+        .unwrap_or_else(|e| panic!("{e}"));
+    if let Some(seed) = args.seed.or(map_seed.map(u64::from)) {
+        *rng = GameRng::new(seed);
+    }
+    info!("RNG seed: {}", rng.seed());
+
+    // Optional wave script
+    let waves = map.get_optional::<String>("waves");
+    if let Some(waves) = waves.unwrap_or_else(|e| panic!("{e}")) {
+        commands.insert_resource(WaveDirector::new(WaveScript::load(&waves)));
+    }
     let mut tileset = None;
     let layer_count = map.layers().len();
     let first_layer_z = FRONT_LAYER_Z - layer_count as f32;
@@ -334,7 +466,21 @@ fn load_tilemap(
                         }
                     }
 
-                    while let Some((&start_point, start_segments)) = points_map.iter_mut().next() {
+                    // Hash map order changes between runs. Start each outline from the lowest
+                    // point left so colliders are built the same way every time. Points are
+                    // only removed from here on, so sorting them once is enough.
+                    let mut start_points = points_map.keys().copied().collect::<Vec<_>>();
+                    start_points.sort_by_key(|p| {
+                        let p = p.get_hashable();
+                        (p.y, p.x)
+                    });
+                    let mut start_points = start_points.into_iter().peekable();
+                    while let Some(&start_point) = start_points.peek() {
+                        // A point can start more than one outline
+                        let Some(start_segments) = points_map.get_mut(&start_point) else {
+                            start_points.next();
+                            continue;
+                        };
                         let mut s = start_segments.swap_remove(0);
                         if start_segments.is_empty() {
                             points_map.remove(&start_point);
@@ -462,80 +608,94 @@ fn load_tilemap(
 
                     match object_data.user_type.as_str() {
                         PLAYER_SPAWN => {
-                            commands
-                                .spawn((PlayerSpawn, TransformBundle::from_transform(transform)));
+                            commands.spawn((
+                                PlayerSpawn,
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
                         }
                         ENEMY_SPAWNER => {
                             commands.spawn((
                                 Spawner::from_object(object_data),
+                                ObjectId(object_data.id()),
                                 TransformBundle::from_transform(
                                     transform
                                         .with_translation(translation.with_z(FRONT_LAYER_Z - 2.5)),
                                 ),
                             ));
                         }
-                        SPAWNER_TRIGGER => {
+                        SPAWNER_TRIGGER | TRIGGER => {
                             println!("{:#?}", object_data);
-                            let angle = f32::to_radians(-object_data.rotation);
-                            let rotation = Quat::from_rotation_z(angle);
-                            transform.rotate(rotation);
-
-                            // This is required because in Tiled, shapes are anchored at their top
-                            // left corner while in Bevy they are anchored at their center.
-                            let update_transform =
-                                |translation: &mut Vec3, half_x: f32, half_y: f32| {
-                                    // north west corner
-                                    let nw_corner = vec2(-half_x, half_y);
-                                    // north west corner after rotation
-                                    let new_nw_corner = Rot2::radians(angle) * nw_corner;
-
-                                    let offset_rotation = nw_corner - new_nw_corner;
-                                    let offset = vec3(half_x, -half_y, 0.0);
-
-                                    *translation += offset_rotation.extend(0.0) + offset;
-                                };
-
-                            let collider = match object_data.shape {
-                                tiled::ObjectShape::Rect { width, height } => {
-                                    let half_x = width * 0.5;
-                                    let half_y = height * 0.5;
-
-                                    update_transform(&mut transform.translation, half_x, half_y);
-                                    Collider::cuboid(half_x, half_y)
-                                }
-                                tiled::ObjectShape::Ellipse { width, height } => {
-                                    let half_x = width * 0.5;
-                                    let half_y = height * 0.5;
-
-                                    update_transform(&mut transform.translation, half_x, half_y);
-
-                                    // There is no ellipse collider but we can create a ball and
-                                    // then set the scale which will convert it into a elliptical
-                                    // polygon if necessary.
-                                    let mut ellipse = Collider::ball(half_x);
-
-                                    let ratio = height / width;
-                                    println!("height: {height}, width: {width}, ratio: {ratio}");
-                                    if ratio != 1.0 {
-                                        // circle -> ellipse
-                                        transform.scale = vec3(1.0, ratio, 1.0);
-                                        ellipse.set_scale(vec2(1.0, ratio), 20);
-                                    }
-                                    ellipse
-                                }
-                                _ => panic!(
-                                    "Trigger shape not supported. Object ID: {}",
-                                    object_data.id()
-                                ),
-                            };
+                            let collider = shape_collider(object_data, &mut transform);
                             commands.spawn((
-                                SpawnerTrigger::from_object(object_data),
+                                TriggerZone::from_object(object_data),
+                                ObjectId(object_data.id()),
                                 collider,
                                 CollisionGroups::new(TRIGGER_GROUP, PLAYER_GROUP),
                                 Sensor,
                                 TransformBundle::from_transform(transform),
                             ));
                         }
+                        DOOR => {
+                            let door = Door::from_object(object_data);
+                            let collider = shape_collider(object_data, &mut transform);
+                            commands.spawn((
+                                door,
+                                ObjectId(object_data.id()),
+                                collider,
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
+                        LEVEL_TEXT => {
+                            commands.spawn((
+                                LevelText::from_object(object_data),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
+                        LEVEL_TIMER => {
+                            commands.spawn((
+                                LevelTimer::from_object(object_data),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                                Name::new("LevelTimer"),
+                            ));
+                        }
+                        MARKER => {
+                            commands.spawn((
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                                Name::new("Marker"),
+                            ));
+                        }
+                        WAGON => {
+                            commands.spawn((
+                                Wagon::new(translation.xy()),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
+                        PATROL_PATH => {
+                            commands.spawn((
+                                PatrolPath::from_object(object_data, translation.xy()),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
+                        PICKUP => {
+                            commands.spawn((
+                                Pickup::from_object(object_data),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
+                        BOSS => {
+                            commands.spawn((
+                                Boss::from_object(object_data, translation.xy()),
+                                ObjectId(object_data.id()),
+                                TransformBundle::from_transform(transform),
+                            ));
+                        }
                         _ => (),
                     }
                 }
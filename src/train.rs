@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::PI;
+
+use crate::{
+    chaser::{update_chaser_avoidance, Chaser, CHASER_RADIUS},
+    config::Config,
+    flowfield::apply_force,
+    patrol::Patroller,
+    physics::SimulationSet,
+    player::Player,
+    shooter::Shooter,
+};
+
+/// Max distance between the centers of neighboring cars
+const CAR_SPACING: f32 = CHASER_RADIUS * 2.5;
+const CAR_FORCE: f32 = 40000.0;
+/// Seconds before cars split by the player can couple again
+const DECOUPLE_COOLDOWN: f32 = 1.5;
+
+/// Chasers that touch link up into trains
+pub struct TrainPlugin;
+
+impl Plugin for TrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (
+                    prepare_couplings,
+                    remove_dead_couplings,
+                    tick_decoupled,
+                    break_trains,
+                    couple_chasers,
+                )
+                    .chain()
+                    .before(steer_train_cars),
+                steer_train_cars
+                    .after(apply_force)
+                    .before(update_chaser_avoidance),
+            )
+                .in_set(SimulationSet),
+        )
+        .register_type::<Coupling>();
+    }
+}
+
+/// Links to neighboring chasers in a train. The head of a train has nothing ahead of it and
+/// follows the flowfield while the other cars follow the car ahead.
+#[derive(Component, Default, Debug, Reflect)]
+pub struct Coupling {
+    pub ahead: Option<Entity>,
+    pub behind: Option<Entity>,
+}
+
+/// Car recently split off a train by the player. Can't couple until the timer finishes, so a
+/// train stays broken while its cars are still touching.
+#[derive(Component, Debug)]
+struct Decoupled(Timer);
+
+impl Decoupled {
+    fn new() -> Self {
+        Decoupled(Timer::from_seconds(DECOUPLE_COOLDOWN, TimerMode::Once))
+    }
+}
+
+/// Lets new chasers couple into trains if trains are enabled. Cars couple when contact starts,
+/// which only needs collision events.
+fn prepare_couplings(
+    mut commands: Commands,
+    config: Res<Config>,
+    q_new: Query<Entity, (Added<Chaser>, Without<Shooter>, Without<Patroller>)>,
+) {
+    if config.max_train_length < 2 {
+        return;
+    }
+    for entity in q_new.iter() {
+        commands
+            .entity(entity)
+            .insert((Coupling::default(), ActiveEvents::COLLISION_EVENTS));
+    }
+}
+
+/// Unlink chasers from neighbors that were despawned
+fn remove_dead_couplings(
+    mut commands: Commands,
+    mut q_coupling: Query<(Entity, &mut Coupling)>,
+    q_chasers: Query<(), With<Chaser>>,
+) {
+    for (entity, mut coupling) in q_coupling.iter_mut() {
+        if coupling.ahead.is_some_and(|e| !q_chasers.contains(e)) {
+            coupling.ahead = None;
+            commands.entity(entity).remove::<ImpulseJoint>();
+        }
+        if coupling.behind.is_some_and(|e| !q_chasers.contains(e)) {
+            coupling.behind = None;
+        }
+    }
+}
+
+fn tick_decoupled(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_decoupled: Query<(Entity, &mut Decoupled)>,
+) {
+    for (entity, mut decoupled) in q_decoupled.iter_mut() {
+        if decoupled.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Decoupled>();
+        }
+    }
+}
+
+/// Detach `entity` from the car ahead of it. Both cars wait [`DECOUPLE_COOLDOWN`] before
+/// coupling again.
+fn decouple(commands: &mut Commands, q_coupling: &mut Query<&mut Coupling>, entity: Entity) {
+    let Ok(mut coupling) = q_coupling.get_mut(entity) else {
+        return;
+    };
+    let Some(ahead) = coupling.ahead.take() else {
+        return;
+    };
+    commands
+        .entity(entity)
+        .remove::<ImpulseJoint>()
+        .try_insert(Decoupled::new());
+    if let Ok(mut coupling) = q_coupling.get_mut(ahead) {
+        coupling.behind = None;
+        commands.entity(ahead).try_insert(Decoupled::new());
+    }
+}
+
+/// A boosting player splits a train at the car they hit.
+fn break_trains(
+    mut commands: Commands,
+    mut contact_events: EventReader<ContactForceEvent>,
+    q_player: Query<(Entity, &Velocity, &Player)>,
+    mut q_coupling: Query<&mut Coupling>,
+) {
+    let Ok((player_entity, velocity, player)) = q_player.get_single() else {
+        return;
+    };
+    if !player.is_boosting(velocity.linvel.length()) {
+        contact_events.clear();
+        return;
+    }
+
+    for event in contact_events.read() {
+        let chaser = if event.collider1 == player_entity {
+            event.collider2
+        } else if event.collider2 == player_entity {
+            event.collider1
+        } else {
+            continue;
+        };
+        let Ok(coupling) = q_coupling.get(chaser) else {
+            continue;
+        };
+        let behind = coupling.behind;
+
+        decouple(&mut commands, &mut q_coupling, chaser);
+        if let Some(behind) = behind {
+            decouple(&mut commands, &mut q_coupling, behind);
+        }
+    }
+}
+
+/// Number of cars in the train formed by coupling `back` behind `front`. Returns `None` if they
+/// are already part of the same train.
+fn coupled_length(q_coupling: &Query<&mut Coupling>, front: Entity, back: Entity) -> Option<usize> {
+    let mut length = 0;
+
+    // Walk to the head of the train
+    let mut car = Some(front);
+    while let Some(entity) = car {
+        if entity == back {
+            return None;
+        }
+        length += 1;
+        car = q_coupling.get(entity).ok().and_then(|c| c.ahead);
+    }
+    // Walk to the tail of the train
+    let mut car = Some(back);
+    while let Some(entity) = car {
+        length += 1;
+        car = q_coupling.get(entity).ok().and_then(|c| c.behind);
+    }
+    Some(length)
+}
+
+/// Chasers that come into contact couple together. The one closer to the player leads. Cars
+/// that were just split off a train are skipped.
+fn couple_chasers(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut collision_events: EventReader<CollisionEvent>,
+    q_player: Query<&Transform, With<Player>>,
+    q_transform: Query<&Transform, (With<Coupling>, Without<Decoupled>)>,
+    mut q_coupling: Query<&mut Coupling>,
+) {
+    if config.max_train_length < 2 {
+        collision_events.clear();
+        return;
+    }
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    for event in collision_events.read() {
+        let &CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        let (Ok(t1), Ok(t2)) = (q_transform.get(collider1), q_transform.get(collider2)) else {
+            continue;
+        };
+        let d1 = t1.translation.xy().distance_squared(player_pos);
+        let d2 = t2.translation.xy().distance_squared(player_pos);
+        let (front, back) = if d1 <= d2 {
+            (collider1, collider2)
+        } else {
+            (collider2, collider1)
+        };
+
+        // Only the tail of a train can have a car attached, and only the head can attach
+        let (Ok(front_coupling), Ok(back_coupling)) = (q_coupling.get(front), q_coupling.get(back))
+        else {
+            continue;
+        };
+        if front_coupling.behind.is_some() || back_coupling.ahead.is_some() {
+            continue;
+        }
+        let Some(length) = coupled_length(&q_coupling, front, back) else {
+            continue;
+        };
+        if length > config.max_train_length {
+            continue;
+        }
+
+        if let Ok(mut coupling) = q_coupling.get_mut(front) {
+            coupling.behind = Some(back);
+        }
+        if let Ok(mut coupling) = q_coupling.get_mut(back) {
+            coupling.ahead = Some(front);
+        }
+        commands
+            .entity(back)
+            .insert(ImpulseJoint::new(front, RopeJointBuilder::new(CAR_SPACING)));
+    }
+}
+
+/// Cars follow the car ahead of them. Overrides the flowfield force set by [`apply_force`].
+fn steer_train_cars(
+    time: Res<Time>,
+    mut q_cars: Query<(Entity, &Coupling, &mut ExternalForce, &mut Transform)>,
+) {
+    let links = q_cars
+        .iter()
+        .filter_map(|(entity, coupling, _, _)| Some((entity, coupling.ahead?)))
+        .collect::<Vec<_>>();
+
+    for (car, ahead) in links {
+        let Ok((_, _, _, ahead_transform)) = q_cars.get(ahead) else {
+            continue;
+        };
+        let ahead_pos = ahead_transform.translation.xy();
+
+        let Ok((_, _, mut force, mut transform)) = q_cars.get_mut(car) else {
+            continue;
+        };
+        let diff = ahead_pos - transform.translation.xy();
+        let Ok(dir) = Dir2::new(diff) else {
+            continue;
+        };
+
+        // Ease off when close so cars don't bunch up
+        let s = (diff.length() / CAR_SPACING).min(1.0);
+        force.force = dir * CAR_FORCE * s;
+
+        // Update rotation to face the direction of travel
+        let target = Quat::from_rotation_z(dir.to_angle() - 0.75 * PI);
+        let s = 6.0 * time.delta_seconds();
+        transform.rotation = transform.rotation.lerp(target, s);
+    }
+}
@@ -0,0 +1,692 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
+use tiled::{ObjectData, PropertyValue};
+
+use crate::{
+    camera::{CameraFocus, MainCamera},
+    door::{Door, DoorAction},
+    physics::SimulationSet,
+    player::ResetEvent,
+    properties::{
+        impl_property_field, object_properties, property_enum, CustomProperties, FromProperty,
+        ObjectRef, PropertyErrorKind,
+    },
+    spawner::{update_spawners, SpawnerAction, SpawnerTriggerEvent},
+    tilemap::ObjectId,
+};
+
+/// Seconds the camera stays on a target when no time is given
+const CAMERA_FOCUS_TIME: f32 = 2.0;
+const LEVEL_TEXT_COLOR: Color = Color::srgb(0.95, 0.95, 0.85);
+const LEVEL_TEXT_Z: f32 = 6.0;
+
+/// Trigger zones, timers and the actions they run on other Tiled objects
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (prepare_level_text, reset_level_objects),
+                (
+                    handle_trigger_events,
+                    update_stay_triggers,
+                    update_level_timers,
+                ),
+                (run_actions, hide_level_text),
+            )
+                .chain()
+                .before(update_spawners)
+                .in_set(SimulationSet),
+        )
+        .add_event::<ActionEvent>()
+        .register_type::<TriggerZone>()
+        .register_type::<LevelTimer>()
+        .register_type::<LevelText>();
+    }
+}
+
+/// Something a trigger or timer does to other objects. Ids are Tiled object ids.
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum Action {
+    Spawner(SpawnerAction, u32),
+    Door(DoorAction, u32),
+    /// Hide again after `secs` if set
+    ShowText {
+        id: u32,
+        secs: Option<f32>,
+    },
+    HideText(u32),
+    /// Point the camera at an object for `secs`
+    Camera {
+        id: u32,
+        secs: f32,
+    },
+    /// Path relative to the assets folder
+    Sound(String),
+    StartTimer(u32),
+    StopTimer(u32),
+    EnableTrigger(u32),
+    DisableTrigger(u32),
+}
+
+impl Action {
+    /// Parse a property like `activate 5 8; open 12; show 14 3.0; sound sounds/alarm.ogg`.
+    /// Verbs that take ids accept several of them.
+    pub fn parse_list(src: &str) -> Result<Vec<Action>, String> {
+        let mut actions = vec![];
+
+        for statement in src.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut words = statement.split_whitespace();
+            let verb = words.next().unwrap_or_default();
+            let args = words.collect::<Vec<_>>();
+
+            let ids = || -> Result<Vec<u32>, String> {
+                let ids = args
+                    .iter()
+                    .map(|arg| arg.parse::<u32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("Expected object ids in \"{statement}\""))?;
+                if ids.is_empty() {
+                    return Err(format!("Missing object id in \"{statement}\""));
+                }
+                Ok(ids)
+            };
+            // An object id followed by an optional number of seconds
+            let id_secs = || -> Result<(u32, Option<f32>), String> {
+                let err = || format!("Expected an object id and seconds in \"{statement}\"");
+                match args[..] {
+                    [id] => Ok((id.parse().map_err(|_| err())?, None)),
+                    [id, secs] => Ok((
+                        id.parse().map_err(|_| err())?,
+                        Some(secs.parse().map_err(|_| err())?),
+                    )),
+                    _ => Err(err()),
+                }
+            };
+
+            let spawner = |action| move |id| Action::Spawner(action, id);
+            let door = |action| move |id| Action::Door(action, id);
+            match verb {
+                "activate" => {
+                    actions.extend(ids()?.into_iter().map(spawner(SpawnerAction::Activate)))
+                }
+                "deactivate" => {
+                    actions.extend(ids()?.into_iter().map(spawner(SpawnerAction::Deactivate)))
+                }
+                "toggle" => actions.extend(ids()?.into_iter().map(spawner(SpawnerAction::Toggle))),
+                "reset" => actions.extend(ids()?.into_iter().map(spawner(SpawnerAction::Reset))),
+                "open" => actions.extend(ids()?.into_iter().map(door(DoorAction::Open))),
+                "close" => actions.extend(ids()?.into_iter().map(door(DoorAction::Close))),
+                "toggle_door" => actions.extend(ids()?.into_iter().map(door(DoorAction::Toggle))),
+                "show" => {
+                    let (id, secs) = id_secs()?;
+                    actions.push(Action::ShowText { id, secs });
+                }
+                "hide" => actions.extend(ids()?.into_iter().map(Action::HideText)),
+                "camera" => {
+                    let (id, secs) = id_secs()?;
+                    let secs = secs.unwrap_or(CAMERA_FOCUS_TIME);
+                    actions.push(Action::Camera { id, secs });
+                }
+                "sound" => match args[..] {
+                    [path] => actions.push(Action::Sound(path.to_string())),
+                    _ => return Err(format!("Expected a file in \"{statement}\"")),
+                },
+                "start" => actions.extend(ids()?.into_iter().map(Action::StartTimer)),
+                "stop" => actions.extend(ids()?.into_iter().map(Action::StopTimer)),
+                "enable" => actions.extend(ids()?.into_iter().map(Action::EnableTrigger)),
+                "disable" => actions.extend(ids()?.into_iter().map(Action::DisableTrigger)),
+                _ => return Err(format!("Unknown action \"{verb}\"")),
+            }
+        }
+        Ok(actions)
+    }
+}
+
+/// Action list written with the syntax of [`Action::parse_list`]
+#[derive(Debug, Clone, Default)]
+struct ActionList(Vec<Action>);
+
+impl FromProperty for ActionList {
+    const TYPE_NAME: &'static str = "string";
+
+    fn from_property(value: &PropertyValue) -> Result<Self, PropertyErrorKind> {
+        let src = String::from_property(value)?;
+        Action::parse_list(&src)
+            .map(ActionList)
+            .map_err(|reason| PropertyErrorKind::Invalid { value: src, reason })
+    }
+}
+
+impl_property_field!(ActionList);
+
+/// Sent when a trigger or timer fires
+#[derive(Event, Debug, Clone)]
+pub struct ActionEvent {
+    /// Object id of the trigger or timer
+    pub source: u32,
+    pub actions: Vec<Action>,
+}
+
+property_enum! {
+    /// Which actions of a [`TriggerZone`] to run
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TriggerCondition {
+        Enter,
+        Exit,
+        Stay,
+    }
+}
+
+object_properties! {
+    /// Custom properties of a [`TriggerZone`] object. Spawner triggers also link spawners with
+    /// `spawner_id` properties.
+    struct TriggerProperties {
+        on_enter: ActionList = ActionList::default(),
+        on_exit: ActionList = ActionList::default(),
+        on_stay: ActionList = ActionList::default(),
+        stay_time: f32 = 0.0,
+        once: bool = false,
+        enabled: bool = true,
+        /// Spawner triggers: what to do to the linked spawners...
+        action: SpawnerAction = SpawnerAction::Activate,
+        /// ...and when
+        fire_on: TriggerCondition = TriggerCondition::Enter,
+    }
+}
+
+/// Sensor zone that runs actions when the player enters, leaves or stays inside.
+#[derive(Component, Debug, Reflect)]
+pub struct TriggerZone {
+    pub on_enter: Vec<Action>,
+    pub on_exit: Vec<Action>,
+    /// Run once per visit after the player stays inside for `stay_time` seconds
+    pub on_stay: Vec<Action>,
+    pub stay_time: f32,
+    /// Only fire once until the level is reset
+    pub once: bool,
+    /// A once-only trigger that has fired
+    pub spent: bool,
+    /// Disabled triggers don't fire. Toggled by other triggers and timers.
+    pub enabled: bool,
+    enabled_default: bool,
+    /// Running while the player is inside a trigger with `on_stay` actions
+    stay_timer: Option<Timer>,
+}
+
+impl TriggerZone {
+    pub fn from_object(object_data: &ObjectData) -> Self {
+        let properties = TriggerProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        let ActionList(mut on_enter) = properties.on_enter;
+        let ActionList(mut on_exit) = properties.on_exit;
+        let ActionList(mut on_stay) = properties.on_stay;
+
+        // Spawner triggers link to spawners with `spawner_id` properties, e.g. `spawner_id`,
+        // `spawner_id2`
+        let mut spawner_ids = vec![];
+        for key in object_data.properties.keys() {
+            if !key.starts_with("spawner_id") {
+                continue;
+            }
+            let id = object_data.get_optional::<ObjectRef>(key);
+            if let Some(ObjectRef(id)) = id.unwrap_or_else(|e| panic!("{e}")) {
+                spawner_ids.push(id);
+            }
+        }
+        spawner_ids.sort_unstable();
+
+        let actions = match properties.fire_on {
+            TriggerCondition::Enter => &mut on_enter,
+            TriggerCondition::Exit => &mut on_exit,
+            TriggerCondition::Stay => &mut on_stay,
+        };
+        actions.extend(
+            spawner_ids
+                .into_iter()
+                .map(|id| Action::Spawner(properties.action, id)),
+        );
+
+        TriggerZone {
+            on_enter,
+            on_exit,
+            on_stay,
+            stay_time: properties.stay_time,
+            once: properties.once,
+            spent: false,
+            enabled: properties.enabled,
+            enabled_default: properties.enabled,
+            stay_timer: None,
+        }
+    }
+
+    /// Ids of spawners this trigger acts on
+    pub fn spawner_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.on_enter
+            .iter()
+            .chain(self.on_exit.iter())
+            .chain(self.on_stay.iter())
+            .filter_map(|action| match *action {
+                Action::Spawner(_, id) => Some(id),
+                _ => None,
+            })
+    }
+
+    /// Build the event for this trigger firing. Returns `None` if the trigger can't fire.
+    fn fire(&mut self, source: u32, condition: TriggerCondition) -> Option<ActionEvent> {
+        let actions = match condition {
+            TriggerCondition::Enter => &self.on_enter,
+            TriggerCondition::Exit => &self.on_exit,
+            TriggerCondition::Stay => &self.on_stay,
+        };
+        if self.spent || !self.enabled || actions.is_empty() {
+            return None;
+        }
+        let actions = actions.clone();
+        self.spent = self.once;
+        Some(ActionEvent { source, actions })
+    }
+}
+
+/// Runs its actions when it finishes. Started by actions or at level start.
+#[derive(Component, Debug, Reflect)]
+pub struct LevelTimer {
+    timer: Timer,
+    running: bool,
+    autostart: bool,
+    on_finish: Vec<Action>,
+}
+
+object_properties! {
+    /// Custom properties of a [`LevelTimer`] object
+    struct TimerProperties {
+        duration: f32,
+        repeat: bool = false,
+        autostart: bool = false,
+        on_finish: ActionList = ActionList::default(),
+    }
+}
+
+impl LevelTimer {
+    pub fn from_object(object_data: &ObjectData) -> Self {
+        let properties = TimerProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        let mode = if properties.repeat {
+            TimerMode::Repeating
+        } else {
+            TimerMode::Once
+        };
+        LevelTimer {
+            timer: Timer::from_seconds(properties.duration, mode),
+            running: properties.autostart,
+            autostart: properties.autostart,
+            on_finish: properties.on_finish.0,
+        }
+    }
+}
+
+/// Text placed in the level that actions show and hide
+#[derive(Component, Debug, Reflect)]
+pub struct LevelText {
+    text: String,
+    font_size: f32,
+    color: Color,
+    visible_default: bool,
+    hide_timer: Option<Timer>,
+}
+
+object_properties! {
+    /// Custom properties of a [`LevelText`] object
+    struct TextProperties {
+        text: String,
+        font_size: f32 = 24.0,
+        color: Color = LEVEL_TEXT_COLOR,
+        visible: bool = false,
+    }
+}
+
+impl LevelText {
+    pub fn from_object(object_data: &ObjectData) -> Self {
+        let properties = TextProperties::read(object_data).unwrap_or_else(|e| panic!("{e}"));
+        LevelText {
+            text: properties.text,
+            font_size: properties.font_size,
+            color: properties.color,
+            visible_default: properties.visible,
+            hide_timer: None,
+        }
+    }
+
+    fn visibility(visible: bool) -> Visibility {
+        if visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        }
+    }
+}
+
+fn prepare_level_text(
+    mut commands: Commands,
+    q_text: Query<(Entity, &LevelText, &Transform), Added<LevelText>>,
+) {
+    for (entity, text, transform) in q_text.iter() {
+        commands.entity(entity).insert((
+            Text2dBundle {
+                text: Text::from_section(
+                    text.text.clone(),
+                    TextStyle {
+                        font_size: text.font_size,
+                        color: text.color,
+                        ..default()
+                    },
+                ),
+                transform: transform.with_translation(transform.translation.with_z(LEVEL_TEXT_Z)),
+                visibility: LevelText::visibility(text.visible_default),
+                ..default()
+            },
+            Name::new("LevelText"),
+        ));
+    }
+}
+
+/// Fire enter and exit actions and start timing stays
+fn handle_trigger_events(
+    mut action_events: EventWriter<ActionEvent>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut q_trigger: Query<(&ObjectId, &mut TriggerZone), With<Sensor>>,
+) {
+    for event in collision_events.read() {
+        let (collider1, collider2, flags) = match *event {
+            CollisionEvent::Started(c1, c2, flags) => (c1, c2, flags),
+            CollisionEvent::Stopped(c1, c2, flags) => (c1, c2, flags),
+        };
+        // Only check SENSOR colliders (trigger has sensor)
+        if !flags.contains(CollisionEventFlags::SENSOR) {
+            continue;
+        }
+        let entered = matches!(event, CollisionEvent::Started(..));
+
+        // If collider is not a trigger, skip
+        let Some(entity) = [collider1, collider2]
+            .into_iter()
+            .find(|&e| q_trigger.contains(e))
+        else {
+            continue;
+        };
+        let (&ObjectId(id), mut trigger) = q_trigger.get_mut(entity).unwrap();
+
+        if !trigger.on_stay.is_empty() {
+            let stay_time = trigger.stay_time;
+            trigger.stay_timer = entered.then(|| Timer::from_seconds(stay_time, TimerMode::Once));
+        }
+        let condition = if entered {
+            TriggerCondition::Enter
+        } else {
+            TriggerCondition::Exit
+        };
+        if let Some(event) = trigger.fire(id, condition) {
+            action_events.send(event);
+        }
+    }
+}
+
+/// Fire stay actions once the player has been inside long enough
+fn update_stay_triggers(
+    time: Res<Time>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut q_trigger: Query<(&ObjectId, &mut TriggerZone)>,
+) {
+    for (&ObjectId(id), mut trigger) in q_trigger.iter_mut() {
+        let Some(timer) = trigger.stay_timer.as_mut() else {
+            continue;
+        };
+        timer.tick(time.delta());
+        if !timer.finished() {
+            continue;
+        }
+        // Player has to leave and enter again to fire another time
+        trigger.stay_timer = None;
+        if let Some(event) = trigger.fire(id, TriggerCondition::Stay) {
+            action_events.send(event);
+        }
+    }
+}
+
+fn update_level_timers(
+    time: Res<Time>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut q_timers: Query<(&ObjectId, &mut LevelTimer)>,
+) {
+    for (&ObjectId(id), mut level_timer) in q_timers.iter_mut() {
+        if !level_timer.running {
+            continue;
+        }
+        level_timer.timer.tick(time.delta());
+        if !level_timer.timer.just_finished() {
+            continue;
+        }
+        if level_timer.timer.mode() == TimerMode::Once {
+            level_timer.running = false;
+        }
+        action_events.send(ActionEvent {
+            source: id,
+            actions: level_timer.on_finish.clone(),
+        });
+    }
+}
+
+/// Apply actions to the objects they reference
+fn run_actions(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut action_events: EventReader<ActionEvent>,
+    mut spawner_events: EventWriter<SpawnerTriggerEvent>,
+    q_objects: Query<(&ObjectId, &GlobalTransform)>,
+    q_camera: Query<Entity, With<MainCamera>>,
+    mut q_doors: Query<(&ObjectId, &mut Door)>,
+    mut q_text: Query<(&ObjectId, &mut LevelText, &mut Visibility)>,
+    mut q_timers: Query<(&ObjectId, &mut LevelTimer)>,
+    mut q_triggers: Query<(&ObjectId, &mut TriggerZone)>,
+) {
+    for event in action_events.read() {
+        for action in event.actions.iter() {
+            match *action {
+                Action::Spawner(action, id) => {
+                    spawner_events.send(SpawnerTriggerEvent {
+                        source: event.source,
+                        action,
+                        spawner_ids: vec![id],
+                    });
+                }
+                Action::Door(action, id) => {
+                    for (_, mut door) in q_doors.iter_mut().filter(|(o, _)| o.0 == id) {
+                        door.apply(action);
+                    }
+                }
+                Action::ShowText { id, secs } => {
+                    for (_, mut text, mut visibility) in
+                        q_text.iter_mut().filter(|(o, ..)| o.0 == id)
+                    {
+                        *visibility = Visibility::Visible;
+                        text.hide_timer =
+                            secs.map(|secs| Timer::from_seconds(secs, TimerMode::Once));
+                    }
+                }
+                Action::HideText(id) => {
+                    for (_, mut text, mut visibility) in
+                        q_text.iter_mut().filter(|(o, ..)| o.0 == id)
+                    {
+                        *visibility = Visibility::Hidden;
+                        text.hide_timer = None;
+                    }
+                }
+                Action::Camera { id, secs } => {
+                    let Some((_, transform)) = q_objects.iter().find(|(o, _)| o.0 == id) else {
+                        warn!("Camera target {id} not found");
+                        continue;
+                    };
+                    for entity in q_camera.iter() {
+                        commands.entity(entity).insert(CameraFocus {
+                            target: transform.translation().xy(),
+                            timer: Timer::from_seconds(secs, TimerMode::Once),
+                        });
+                    }
+                }
+                Action::Sound(ref path) => {
+                    commands.spawn(AudioBundle {
+                        source: asset_server.load(path.clone()),
+                        settings: PlaybackSettings::DESPAWN,
+                    });
+                }
+                Action::StartTimer(id) => {
+                    for (_, mut level_timer) in q_timers.iter_mut().filter(|(o, _)| o.0 == id) {
+                        level_timer.timer.reset();
+                        level_timer.running = true;
+                    }
+                }
+                Action::StopTimer(id) => {
+                    for (_, mut level_timer) in q_timers.iter_mut().filter(|(o, _)| o.0 == id) {
+                        level_timer.running = false;
+                    }
+                }
+                Action::EnableTrigger(id) => {
+                    for (_, mut trigger) in q_triggers.iter_mut().filter(|(o, _)| o.0 == id) {
+                        trigger.enabled = true;
+                    }
+                }
+                Action::DisableTrigger(id) => {
+                    for (_, mut trigger) in q_triggers.iter_mut().filter(|(o, _)| o.0 == id) {
+                        trigger.enabled = false;
+                        trigger.stay_timer = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn hide_level_text(time: Res<Time>, mut q_text: Query<(&mut LevelText, &mut Visibility)>) {
+    for (mut text, mut visibility) in q_text.iter_mut() {
+        let Some(timer) = text.hide_timer.as_mut() else {
+            continue;
+        };
+        timer.tick(time.delta());
+        if timer.finished() {
+            text.hide_timer = None;
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+/// Re-arm triggers and restore timers, text and camera
+fn reset_level_objects(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    mut q_triggers: Query<&mut TriggerZone>,
+    mut q_timers: Query<&mut LevelTimer>,
+    mut q_text: Query<(&mut LevelText, &mut Visibility)>,
+    q_camera: Query<Entity, With<CameraFocus>>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for mut trigger in q_triggers.iter_mut() {
+        trigger.spent = false;
+        trigger.enabled = trigger.enabled_default;
+        trigger.stay_timer = None;
+    }
+    for mut level_timer in q_timers.iter_mut() {
+        level_timer.timer.reset();
+        level_timer.running = level_timer.autostart;
+    }
+    for (mut text, mut visibility) in q_text.iter_mut() {
+        text.hide_timer = None;
+        *visibility = LevelText::visibility(text.visible_default);
+    }
+    for entity in q_camera.iter() {
+        commands.entity(entity).remove::<CameraFocus>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_action_list() {
+        let actions = Action::parse_list(
+            "activate 5 8; open 12;show 14 3.0; hide 14; camera 7; sound sounds/alarm.ogg;",
+        )
+        .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                Action::Spawner(SpawnerAction::Activate, 5),
+                Action::Spawner(SpawnerAction::Activate, 8),
+                Action::Door(DoorAction::Open, 12),
+                Action::ShowText {
+                    id: 14,
+                    secs: Some(3.0)
+                },
+                Action::HideText(14),
+                Action::Camera {
+                    id: 7,
+                    secs: CAMERA_FOCUS_TIME
+                },
+                Action::Sound("sounds/alarm.ogg".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_action_list() {
+        assert_eq!(Action::parse_list(""), Ok(vec![]));
+        assert_eq!(Action::parse_list(" ; ;"), Ok(vec![]));
+    }
+
+    #[test]
+    fn rejects_unknown_action() {
+        assert_eq!(
+            Action::parse_list("open 3; explode 4"),
+            Err("Unknown action \"explode\"".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            Action::parse_list("activate"),
+            Err("Missing object id in \"activate\"".to_string())
+        );
+        assert_eq!(
+            Action::parse_list("open door"),
+            Err("Expected object ids in \"open door\"".to_string())
+        );
+        assert_eq!(
+            Action::parse_list("show 3 soon"),
+            Err("Expected an object id and seconds in \"show 3 soon\"".to_string())
+        );
+        assert_eq!(
+            Action::parse_list("camera 1 2 3"),
+            Err("Expected an object id and seconds in \"camera 1 2 3\"".to_string())
+        );
+        assert_eq!(
+            Action::parse_list("sound"),
+            Err("Expected a file in \"sound\"".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_action_list_property() {
+        let value = PropertyValue::StringValue("open x".to_string());
+        let err = ActionList::from_property(&value).unwrap_err();
+        assert_eq!(
+            err,
+            PropertyErrorKind::Invalid {
+                value: "open x".to_string(),
+                reason: "Expected object ids in \"open x\"".to_string(),
+            }
+        );
+    }
+}
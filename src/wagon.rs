@@ -0,0 +1,276 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{
+    chaser::Chaser,
+    physics::{InterpolatedTransform, SimulationSet, CHASER_GROUP, PLAYER_GROUP, WAGON_GROUP},
+    player::{Player, ResetEvent},
+};
+
+const WAGON_SIZE: Vec2 = Vec2::new(14.0, 10.0);
+const WAGON_COLOR: Color = Color::srgb(0.3, 0.9, 0.4);
+const WAGON_Z: f32 = 4.0;
+/// Distance between the centers of neighboring wagons
+const WAGON_SPACING: f32 = 18.0;
+/// Player position is recorded every time they move this far
+const HISTORY_SAMPLE_DIST: f32 = 2.0;
+/// Number of history samples between neighboring wagons
+const SAMPLES_PER_WAGON: usize = (WAGON_SPACING / HISTORY_SAMPLE_DIST) as usize;
+
+/// Wagons placed in Tiled that the player collects and pulls behind them
+pub struct WagonPlugin;
+
+impl Plugin for WagonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (setup, spawn_wagon_text))
+            .add_systems(
+                Update,
+                (
+                    (prepare_player, prepare_wagons),
+                    (handle_wagon_collisions, reset_wagons).chain(),
+                    update_wagon_ui,
+                ),
+            )
+            // Follow the simulated player. Wagons are interpolated like physics bodies.
+            .add_systems(FixedUpdate, follow_player.in_set(SimulationSet))
+            .register_type::<Wagon>();
+    }
+}
+
+/// Collectible car. Spawned from the Tiled object layer.
+#[derive(Component, Debug, Reflect)]
+pub struct Wagon {
+    /// Where the wagon was placed in the level
+    home: Vec2,
+    attached: bool,
+}
+
+impl Wagon {
+    pub fn new(home: Vec2) -> Self {
+        Wagon {
+            home,
+            attached: false,
+        }
+    }
+}
+
+/// Wagons attached to the player, front to back.
+#[derive(Component, Debug, Default)]
+pub struct WagonTrain {
+    pub wagons: Vec<Entity>,
+    /// Recent player positions, newest first. Wagons are placed along this so they trace the
+    /// player's route through corridors.
+    history: VecDeque<Vec2>,
+}
+
+impl WagonTrain {
+    /// Each wagon adds 1 to the score multiplier, starting from 1 without wagons
+    pub fn score_multiplier(&self) -> u32 {
+        1 + self.wagons.len() as u32
+    }
+}
+
+/// Draws wagon count on screen
+#[derive(Component)]
+struct WagonUi;
+
+#[derive(Resource)]
+struct WagonAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(WagonAssets {
+        mesh: Mesh2dHandle(meshes.add(Rectangle::from_size(WAGON_SIZE))),
+        material: materials.add(ColorMaterial::from_color(WAGON_COLOR)),
+    });
+}
+
+fn spawn_wagon_text(mut commands: Commands) {
+    commands.spawn((
+        WagonUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: WAGON_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+fn prepare_player(mut commands: Commands, q_player: Query<Entity, Added<Player>>) {
+    for entity in q_player.iter() {
+        commands.entity(entity).insert(WagonTrain::default());
+    }
+}
+
+/// Add visuals and sensor collider to wagons loaded from the tilemap
+fn prepare_wagons(
+    mut commands: Commands,
+    assets: Res<WagonAssets>,
+    q_wagons: Query<(Entity, &Transform), Added<Wagon>>,
+) {
+    for (entity, transform) in q_wagons.iter() {
+        let transform = transform.with_translation(transform.translation.with_z(WAGON_Z));
+        commands.entity(entity).insert((
+            MaterialMesh2dBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                transform,
+                ..default()
+            },
+            InterpolatedTransform::new(&transform),
+            Collider::cuboid(WAGON_SIZE.x * 0.5, WAGON_SIZE.y * 0.5),
+            Sensor,
+            CollisionGroups::new(WAGON_GROUP, PLAYER_GROUP | CHASER_GROUP),
+            ActiveEvents::COLLISION_EVENTS,
+            Name::new("Wagon"),
+        ));
+    }
+}
+
+/// Player collects free wagons and chasers knock off attached wagons along with every wagon
+/// behind them.
+fn handle_wagon_collisions(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut q_train: Query<(Entity, &mut WagonTrain), With<Player>>,
+    mut q_wagons: Query<&mut Wagon>,
+    q_chasers: Query<(), With<Chaser>>,
+) {
+    let Ok((player_entity, mut train)) = q_train.get_single_mut() else {
+        return;
+    };
+
+    for event in collision_events.read() {
+        let &CollisionEvent::Started(collider1, collider2, _) = event else {
+            continue;
+        };
+        let (entity, other) = if q_wagons.contains(collider1) {
+            (collider1, collider2)
+        } else if q_wagons.contains(collider2) {
+            (collider2, collider1)
+        } else {
+            continue;
+        };
+        let Ok(mut wagon) = q_wagons.get_mut(entity) else {
+            continue;
+        };
+
+        if other == player_entity && !wagon.attached {
+            wagon.attached = true;
+            train.wagons.push(entity);
+            // Attached wagons can't be collected again
+            commands
+                .entity(entity)
+                .insert(CollisionGroups::new(WAGON_GROUP, CHASER_GROUP));
+        } else if wagon.attached && q_chasers.contains(other) {
+            let Some(index) = train.wagons.iter().position(|&e| e == entity) else {
+                continue;
+            };
+            for detached in train.wagons.drain(index..) {
+                if let Ok(mut wagon) = q_wagons.get_mut(detached) {
+                    wagon.attached = false;
+                }
+                commands.entity(detached).insert(CollisionGroups::new(
+                    WAGON_GROUP,
+                    PLAYER_GROUP | CHASER_GROUP,
+                ));
+            }
+        }
+    }
+}
+
+/// Detach all wagons and move them back to where they were placed
+fn reset_wagons(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetEvent>,
+    mut q_train: Query<&mut WagonTrain>,
+    mut q_wagons: Query<(Entity, &mut Wagon, &mut Transform)>,
+) {
+    if reset_events.read().last().is_none() {
+        return;
+    }
+    for mut train in q_train.iter_mut() {
+        train.wagons.clear();
+        train.history.clear();
+    }
+    for (entity, mut wagon, mut transform) in q_wagons.iter_mut() {
+        wagon.attached = false;
+        transform.translation = wagon.home.extend(WAGON_Z);
+        transform.rotation = Quat::IDENTITY;
+        commands.entity(entity).insert(CollisionGroups::new(
+            WAGON_GROUP,
+            PLAYER_GROUP | CHASER_GROUP,
+        ));
+    }
+}
+
+/// Record player path and place attached wagons along it.
+fn follow_player(
+    mut q_train: Query<(&mut WagonTrain, &Transform), With<Player>>,
+    mut q_wagons: Query<&mut Transform, (With<Wagon>, Without<Player>)>,
+) {
+    let Ok((mut train, player_transform)) = q_train.get_single_mut() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    // Record new sample once player has moved far enough
+    let stationary = train
+        .history
+        .front()
+        .is_some_and(|&last| last.distance(player_pos) < HISTORY_SAMPLE_DIST);
+    if !stationary {
+        train.history.push_front(player_pos);
+    }
+    // Only keep as much history as the wagons need
+    let max_len = (train.wagons.len() + 1) * SAMPLES_PER_WAGON + 1;
+    train.history.truncate(max_len);
+
+    let train = &*train;
+    for (i, &entity) in train.wagons.iter().enumerate() {
+        let Ok(mut transform) = q_wagons.get_mut(entity) else {
+            continue;
+        };
+        let index = ((i + 1) * SAMPLES_PER_WAGON).min(train.history.len() - 1);
+        let target = train.history[index];
+
+        // Face the direction of travel
+        let ahead = train.history[index.saturating_sub(1)];
+        if let Ok(dir) = Dir2::new(ahead - target) {
+            transform.rotation = Quat::from_rotation_z(dir.to_angle());
+        }
+        transform.translation = target.extend(WAGON_Z);
+    }
+}
+
+fn update_wagon_ui(mut text_query: Query<&mut Text, With<WagonUi>>, q_train: Query<&WagonTrain>) {
+    let Ok(train) = q_train.get_single() else {
+        return;
+    };
+    let value = format!("wagons: {}", train.wagons.len());
+
+    // Avoid updating text layout every frame
+    let mut text = text_query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}